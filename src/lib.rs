@@ -1,11 +1,15 @@
 use core::fmt;
 use std::vec;
 
+use heck::ToUpperCamelCase;
 use proc_macro::TokenStream;
 use proc_macro2::Span;
 use queries::{
-    GeneratedInputStruct, InputQuery, InputSetter, InputSetterWithDurability, Intern, Lookup,
-    Queries, SetterKind, TrackedQuery, Transparent,
+    AccessorKind, Accumulated, BatchUpdate, Cycle, GeneratedInputStruct, InputHas,
+    InputMetadataGetter, InputObserver, InputQuery, InputSetter, InputSetterWithDurability,
+    InputStorage, InputTryGetter, InputTrySetter, InputUnset, InputUpdate, InputsBuilder,
+    InputsJournal, InputsMetadata, InputsSnapshot, Intern, JournalAction, Lookup, Queries,
+    SetterKind, TrackedQuery, Transparent, Validator,
 };
 use quote::{format_ident, quote, ToTokens};
 use syn::spanned::Spanned;
@@ -17,7 +21,7 @@ mod queries;
 #[proc_macro_attribute]
 pub fn query_group(args: TokenStream, input: TokenStream) -> TokenStream {
     match query_group_impl(args, input.clone()) {
-        Ok(tokens) => tokens.into(),
+        Ok(tokens) => tokens,
         Err(e) => token_stream_with_error(input, e),
     }
 }
@@ -26,6 +30,9 @@ pub fn query_group(args: TokenStream, input: TokenStream) -> TokenStream {
 struct InputStructField {
     name: proc_macro2::TokenStream,
     ty: proc_macro2::TokenStream,
+    /// The value the field starts out with in `create_data_{Trait}`.
+    init: proc_macro2::TokenStream,
+    attrs: proc_macro2::TokenStream,
 }
 
 impl fmt::Display for InputStructField {
@@ -104,18 +111,18 @@ enum QueryKind {
     TrackedWithSalsaStruct,
     Transparent,
     Interned,
+    Accumulated,
 }
 
 pub(crate) fn query_group_impl(
-    _args: proc_macro::TokenStream,
+    args: proc_macro::TokenStream,
     input: proc_macro::TokenStream,
 ) -> Result<proc_macro::TokenStream, syn::Error> {
-    let mut item_trait = match syn::parse::<ItemTrait>(input) {
-        Ok(path) => path,
-        Err(e) => return Err(e),
-    };
+    let options = syn::parse::<QueryGroupOptions>(args)?;
+
+    let mut item_trait = syn::parse::<ItemTrait>(input)?;
 
-    let supertraits = &item_trait.supertraits;
+    let supertraits = &item_trait.supertraits.clone();
 
     let db_attr: Attribute = parse_quote! {
         #[salsa::db]
@@ -125,261 +132,626 @@ pub(crate) fn query_group_impl(
     let trait_name_ident = &item_trait.ident.clone();
     let input_struct_name = format_ident!("{}Data", trait_name_ident);
     let create_data_ident = format_ident!("create_data_{}", trait_name_ident);
+    let journal_entry = options
+        .journal
+        .map(|_| InputsJournal::entry_name(trait_name_ident));
 
     let mut input_struct_fields: Vec<InputStructField> = vec![];
     let mut trait_methods = vec![];
     let mut setter_trait_methods = vec![];
+    let mut accessor_trait_methods = vec![];
     let mut lookup_signatures = vec![];
     let mut lookup_methods = vec![];
+    let mut slot_structs = vec![];
+    let mut inputs = vec![];
 
     for item in item_trait.clone().items {
-        match item {
-            syn::TraitItem::Fn(method) => {
-                let method_name = &method.sig.ident;
-                let signature = &method.sig.clone();
-
-                let (_attrs, salsa_attrs) = filter_attrs(method.attrs);
-
-                let mut query_kind = QueryKind::Tracked;
-                let mut invoke = None;
-                let mut cycle = None;
-                let mut interned_struct_path = None;
-                let mut lru = None;
+        let syn::TraitItem::Fn(method) = item else {
+            continue;
+        };
+        let method_name = &method.sig.ident;
+        let signature = &method.sig.clone();
 
-                let params: Vec<FnArg> = signature.inputs.clone().into_iter().collect();
-                let pat_and_tys = params
-                    .into_iter()
-                    .filter(|fn_arg| matches!(fn_arg, FnArg::Typed(_)))
-                    .map(|fn_arg| match fn_arg {
-                        FnArg::Typed(pat_type) => pat_type.clone(),
-                        FnArg::Receiver(_) => unreachable!("this should have been filtered out"),
-                    })
-                    .collect::<Vec<syn::PatType>>();
-
-                for SalsaAttr { name, tts, span } in salsa_attrs {
-                    match name.as_str() {
-                        "cycle" => {
-                            let path = match syn::parse::<Parenthesized<Path>>(tts) {
-                                Ok(path) => path,
-                                Err(e) => return Err(e),
-                            };
-                            cycle = Some(path.0.clone())
-                        }
-                        "input" => {
-                            if !pat_and_tys.is_empty() {
-                                return Err(syn::Error::new(
-                                    span,
-                                    "input methods cannot have a parameter",
-                                ));
-                            }
-                            query_kind = QueryKind::Input;
-                        }
-                        "interned" => {
-                            let syn::ReturnType::Type(_, ty) = &signature.output else {
-                                return Err(syn::Error::new(
-                                    span,
-                                    "interned queries must have return type",
-                                ));
-                            };
-                            let syn::Type::Path(path) = &**ty else {
-                                return Err(syn::Error::new(
-                                    span,
-                                    "interned queries must have return type",
-                                ));
-                            };
-                            interned_struct_path = Some(path.path.clone());
-                            query_kind = QueryKind::Interned;
-                        }
-                        "invoke" => {
-                            let path = match syn::parse::<Parenthesized<Path>>(tts) {
-                                Ok(path) => path,
-                                Err(e) => return Err(e),
-                            };
-                            invoke = Some(path.0.clone());
-                        }
-                        "invoke_actual" => {
-                            let path = match syn::parse::<Parenthesized<Path>>(tts) {
-                                Ok(path) => path,
-                                Err(e) => return Err(e),
-                            };
-                            invoke = Some(path.0.clone());
-                            query_kind = QueryKind::TrackedWithSalsaStruct;
-                        }
-                        "lru" => {
-                            let lru_count = match syn::parse::<Parenthesized<syn::LitInt>>(tts) {
-                                Ok(path) => path,
-                                Err(e) => return Err(e),
-                            };
-                            let value = lru_count.0.base10_parse::<u32>()?;
-
-                            lru = Some(value);
-                        }
-                        "transparent" => {
-                            query_kind = QueryKind::Transparent;
-                        }
-                        _ => {
-                            return Err(syn::Error::new(
-                                span.clone(),
-                                format!("unknown attribute `{name}`"),
-                            ))
-                        }
-                    }
-                }
+        let (_attrs, salsa_attrs) = filter_attrs(method.attrs);
 
-                let syn::ReturnType::Type(_, return_ty) = signature.output.clone() else {
-                    return Err(syn::Error::new(
-                        signature.span(),
-                        "Queries must have a return type",
-                    ));
-                };
+        let mut query_kind = QueryKind::Tracked;
+        let mut invoke = None;
+        let mut cycle = None;
+        let mut interned_struct_path = None;
+        let mut lru = None;
+        let mut dependencies = false;
+        let mut tracked_options = vec![];
+        let mut tracked_return_ref = false;
+        // attributes that only make sense on tracked queries, checked once the
+        // kind of query is known.
+        let mut tracked_only = vec![];
+        let mut input_options = InputOptions::default();
+        let mut accumulated = None;
 
-                if let syn::Type::Path(ref ty_path) = *return_ty {
-                    if matches!(query_kind, QueryKind::Input) {
-                        let field = InputStructField {
-                            name: method_name.to_token_stream(),
-                            ty: ty_path.path.to_token_stream(),
-                        };
+        let params: Vec<FnArg> = signature.inputs.clone().into_iter().collect();
+        let pat_and_tys = params
+            .into_iter()
+            .filter(|fn_arg| matches!(fn_arg, FnArg::Typed(_)))
+            .map(|fn_arg| match fn_arg {
+                FnArg::Typed(pat_type) => pat_type.clone(),
+                FnArg::Receiver(_) => unreachable!("this should have been filtered out"),
+            })
+            .collect::<Vec<syn::PatType>>();
 
-                        input_struct_fields.push(field);
-                    }
+        for SalsaAttr { name, tts, span } in salsa_attrs {
+            match name.as_str() {
+                "accumulated" => {
+                    accumulated = Some(syn::parse::<Parenthesized<AccumulatedOptions>>(tts)?.0);
+                    query_kind = QueryKind::Accumulated;
                 }
-
-                match (query_kind, invoke) {
-                    // input
-                    (QueryKind::Input, None) => {
-                        let query = InputQuery {
-                            signature: method.sig.clone(),
-                            create_data_ident: create_data_ident.clone(),
-                        };
-                        let value = Queries::InputQuery(query);
-                        trait_methods.push(value);
-
-                        let setter = InputSetter {
-                            signature: method.sig.clone(),
-                            return_type: *return_ty.clone(),
-                            create_data_ident: create_data_ident.clone(),
-                        };
-                        setter_trait_methods.push(SetterKind::Plain(setter));
-
-                        let setter = InputSetterWithDurability {
-                            signature: method.sig.clone(),
-                            return_type: *return_ty.clone(),
-                            create_data_ident: create_data_ident.clone(),
-                        };
-                        setter_trait_methods.push(SetterKind::WithDurability(setter));
-                    }
-                    (QueryKind::Interned, None) => {
-                        let interned_struct_path = interned_struct_path.unwrap();
-                        let method = Intern {
-                            signature: signature.clone(),
-                            pat_and_tys: pat_and_tys.clone(),
-                            interned_struct_path: interned_struct_path.clone(),
-                        };
-
-                        trait_methods.push(Queries::Intern(method));
-
-                        let mut method = Lookup {
-                            signature: signature.clone(),
-                            pat_and_tys: pat_and_tys.clone(),
-                            return_ty: *return_ty,
-                            interned_struct_path,
-                        };
-                        method.prepare_signature();
-
-                        lookup_signatures
-                            .push(TraitItem::Fn(make_trait_method(method.signature.clone())));
-                        lookup_methods.push(method);
-                    }
-                    // tracked function without *any* invoke.
-                    (QueryKind::Tracked, None) => {
-                        let method = TrackedQuery {
-                            trait_name: trait_name_ident.clone(),
-                            generated_struct: Some(GeneratedInputStruct {
-                                input_struct_name: input_struct_name.clone(),
-                                create_data_ident: create_data_ident.clone(),
-                            }),
-                            signature: signature.clone(),
-                            pat_and_tys: pat_and_tys.clone(),
-                            invoke: None,
-                            cycle,
-                            lru,
-                        };
-
-                        trait_methods.push(Queries::TrackedQuery(method));
-                    }
-                    // tracked function with an invoke
-                    (QueryKind::Tracked, Some(invoke)) => {
-                        let method = TrackedQuery {
-                            trait_name: trait_name_ident.clone(),
-                            generated_struct: Some(GeneratedInputStruct {
-                                input_struct_name: input_struct_name.clone(),
-                                create_data_ident: create_data_ident.clone(),
-                            }),
-                            signature: signature.clone(),
-                            pat_and_tys: pat_and_tys.clone(),
-                            invoke: Some(invoke),
-                            cycle,
-                            lru,
-                        };
-
-                        trait_methods.push(Queries::TrackedQuery(method))
-                    }
-                    (QueryKind::TrackedWithSalsaStruct, Some(invoke)) => {
-                        let method = TrackedQuery {
-                            trait_name: trait_name_ident.clone(),
-                            generated_struct: None,
-                            signature: signature.clone(),
-                            pat_and_tys: pat_and_tys.clone(),
-                            invoke: Some(invoke),
-                            cycle,
-                            lru,
-                        };
-
-                        trait_methods.push(Queries::TrackedQuery(method))
+                "cycle" => {
+                    let options = syn::parse::<Parenthesized<Cycle>>(tts)?;
+                    cycle = Some(options.0)
+                }
+                "dependencies" => {
+                    dependencies = true;
+                }
+                "input" => {
+                    input_options = parse_options::<InputOptions>(tts)?;
+                    if input_options.return_ref && input_options.default.is_some() {
+                        return Err(syn::Error::new(
+                            span,
+                            "`return_ref` inputs cannot have a `default`",
+                        ));
                     }
-                    (QueryKind::TrackedWithSalsaStruct, None) => unreachable!(),
-                    (QueryKind::Transparent, None) => {
-                        let method = Transparent {
-                            signature: method.sig.clone(),
-                            pat_and_tys: pat_and_tys.clone(),
-                            invoke: None,
-                        };
-                        trait_methods.push(Queries::Transparent(method));
+                    if input_options.untracked && input_options.return_ref {
+                        return Err(syn::Error::new(
+                            span,
+                            "`untracked` inputs cannot be `return_ref`",
+                        ));
                     }
-                    (QueryKind::Transparent, Some(invoke)) => {
-                        let method = Transparent {
-                            signature: method.sig.clone(),
-                            pat_and_tys: pat_and_tys.clone(),
-                            invoke: Some(invoke),
-                        };
-                        trait_methods.push(Queries::Transparent(method));
+                    if input_options.load.is_some()
+                        && (input_options.default.is_some() || input_options.untracked)
+                    {
+                        return Err(syn::Error::new(
+                            span,
+                            "`load` inputs cannot have a `default` or be `untracked`",
+                        ));
                     }
-                    // error/invalid constructions
-                    (QueryKind::Interned, Some(path)) => {
+                    if input_options.untracked && !pat_and_tys.is_empty() {
                         return Err(syn::Error::new(
-                            path.span(),
-                            format!("Interned queries cannot be used with an `#[invoke]`"),
-                        ))
+                            span,
+                            "`untracked` inputs cannot have parameters",
+                        ));
                     }
-                    (QueryKind::Input, Some(path)) => {
+                    query_kind = QueryKind::Input;
+                }
+                "interned" => {
+                    let syn::ReturnType::Type(_, ty) = &signature.output else {
+                        return Err(syn::Error::new(
+                            span,
+                            "interned queries must have return type",
+                        ));
+                    };
+                    let syn::Type::Path(path) = &**ty else {
                         return Err(syn::Error::new(
-                            path.span(),
-                            format!("Inputs cannot be used with an `#[invoke]`"),
-                        ))
+                            span,
+                            "interned queries must have return type",
+                        ));
+                    };
+                    interned_struct_path = Some(path.path.clone());
+                    query_kind = QueryKind::Interned;
+                }
+                "invoke" => {
+                    let path = syn::parse::<Parenthesized<Path>>(tts)?;
+                    invoke = Some(path.0.clone());
+                }
+                "invoke_actual" => {
+                    let path = syn::parse::<Parenthesized<Path>>(tts)?;
+                    invoke = Some(path.0.clone());
+                    query_kind = QueryKind::TrackedWithSalsaStruct;
+                }
+                "lru" => {
+                    let lru_count = syn::parse::<Parenthesized<syn::LitInt>>(tts)?;
+                    let value = lru_count.0.base10_parse::<u32>()?;
+
+                    lru = Some(value);
+                }
+                "tracked_options" => {
+                    tracked_options.extend(parse_options::<TrackedOptions>(tts)?.0);
+                    tracked_only.push((span, name));
+                }
+                "return_ref" => {
+                    tracked_return_ref = true;
+                    tracked_only.push((span, name));
+                }
+                "transparent" => {
+                    query_kind = QueryKind::Transparent;
+                }
+                _ => return Err(syn::Error::new(span, format!("unknown attribute `{name}`"))),
+            }
+        }
+
+        if let Some((span, name)) = tracked_only.first() {
+            if !matches!(
+                query_kind,
+                QueryKind::Tracked | QueryKind::TrackedWithSalsaStruct
+            ) {
+                return Err(syn::Error::new(
+                    *span,
+                    format!("`{name}` can only be used on tracked queries"),
+                ));
+            }
+        }
+
+        check_tracked_options(
+            &tracked_options,
+            &[
+                ("lru", lru.is_some() || dependencies),
+                ("no_eq", dependencies),
+                ("return_ref", tracked_return_ref),
+                ("recovery_fn", cycle.is_some()),
+            ],
+        )?;
+
+        let syn::ReturnType::Type(_, return_ty) = signature.output.clone() else {
+            return Err(syn::Error::new(
+                signature.span(),
+                "Queries must have a return type",
+            ));
+        };
+
+        let input_storage = InputStorage {
+            trait_name: trait_name_ident.clone(),
+            field: method_name.clone(),
+            index: input_struct_fields.len(),
+            ty: *return_ty.clone(),
+            keys: pat_and_tys.clone(),
+            slot_struct_name: format_ident!(
+                "{}{}Slot",
+                trait_name_ident,
+                method_name.to_string().to_upper_camel_case()
+            ),
+            create_data_ident: create_data_ident.clone(),
+            durability: input_options.durability.clone(),
+            default: input_options.default.clone(),
+            skip_unchanged: input_options.skip_unchanged,
+            validate: input_options.validate.clone(),
+            journal: journal_entry.clone(),
+            return_ref: input_options.return_ref,
+            untracked: input_options.untracked,
+            load: input_options.load.clone(),
+            observe: options.observer,
+        };
+
+        if matches!(query_kind, QueryKind::Input) {
+            check_input_type(&return_ty)?;
+
+            let ty = return_ty.to_token_stream();
+            let return_ref = input_options.return_ref.then(|| quote!(#[return_ref]));
+            let init = match &input_options.default {
+                Some(default) => quote!(Some(#default)),
+                None => quote!(None),
+            };
+            let field = if input_storage.is_keyed() {
+                // keyed inputs store one salsa input per key, looked up through a map;
+                // only adding a key changes the map itself.
+                let slot_struct_name = &input_storage.slot_struct_name;
+                let key_ty = input_storage.key_ty();
+                slot_structs.push(quote! {
+                    #[salsa::input]
+                    pub(crate) struct #slot_struct_name {
+                        #return_ref
+                        value: Option<#ty>,
                     }
+                });
+
+                InputStructField {
+                    name: method_name.to_token_stream(),
+                    ty: quote!(std::collections::HashMap<#key_ty, #slot_struct_name>),
+                    init: quote!(std::collections::HashMap::default()),
+                    attrs: quote!(#[return_ref]),
+                }
+            } else if input_storage.untracked {
+                // the field keeps pointing at the same lock, so writing to the lock
+                // doesn't invalidate the queries that read it.
+                InputStructField {
+                    name: method_name.to_token_stream(),
+                    ty: quote!(std::sync::Arc<std::sync::RwLock<Option<#ty>>>),
+                    init: quote!(std::sync::Arc::new(std::sync::RwLock::new(#init))),
+                    attrs: quote!(#[return_ref]),
+                }
+            } else {
+                InputStructField {
+                    name: method_name.to_token_stream(),
+                    ty: quote!(Option<#ty>),
+                    init,
+                    attrs: quote!(#return_ref),
+                }
+            };
+
+            input_struct_fields.push(field);
+        }
+
+        if tracked_return_ref {
+            return_by_ref(&mut item_trait, method_name, &return_ty);
+        }
+
+        match (query_kind, invoke) {
+            // input
+            (QueryKind::Input, None) => {
+                inputs.push(input_storage.clone());
+
+                // `return_ref` getters borrow the value from salsa's storage.
+                let mut getter_ty = *return_ty.clone();
+                let mut getter_signature = method.sig.clone();
+                if input_options.return_ref {
+                    getter_ty = parse_quote!(&#return_ty);
+                    getter_signature.output = parse_quote!(-> #getter_ty);
+                    return_by_ref(&mut item_trait, method_name, &return_ty);
+                }
+
+                let query = InputQuery {
+                    signature: getter_signature,
+                    storage: input_storage.clone(),
+                    report_untracked_read: false,
+                };
+                let value = Queries::InputQuery(query);
+                trait_methods.push(value);
+
+                if input_options.untracked {
+                    let accessor = InputQuery {
+                        signature: method.sig.clone(),
+                        storage: input_storage.clone(),
+                        report_untracked_read: true,
+                    };
+                    accessor_trait_methods.push(AccessorKind::Tracked(accessor));
+                }
+
+                for if_changed in [false, true] {
+                    let setter = InputSetter {
+                        signature: method.sig.clone(),
+                        return_type: *return_ty.clone(),
+                        storage: input_storage.clone(),
+                        if_changed,
+                    };
+                    setter_trait_methods.push(SetterKind::Plain(setter));
+                }
+
+                let setter = InputSetterWithDurability {
+                    signature: method.sig.clone(),
+                    return_type: *return_ty.clone(),
+                    storage: input_storage.clone(),
+                };
+                setter_trait_methods.push(SetterKind::WithDurability(setter));
+
+                let setter = InputUnset {
+                    signature: method.sig.clone(),
+                    storage: input_storage.clone(),
+                };
+                setter_trait_methods.push(SetterKind::Unset(setter));
+
+                if let Some(validate) = input_options.validate {
+                    let setter = InputTrySetter {
+                        signature: method.sig.clone(),
+                        storage: input_storage.clone(),
+                        validate,
+                    };
+                    setter_trait_methods.push(SetterKind::TrySet(setter));
+                }
+
+                for with_durability in [false, true] {
+                    let setter = InputUpdate {
+                        signature: method.sig.clone(),
+                        storage: input_storage.clone(),
+                        with_durability,
+                    };
+                    setter_trait_methods.push(SetterKind::Update(setter));
+                }
+
+                // untracked inputs also get `try_{input}_tracked` and
+                // `has_{input}_tracked`, like `{input}_tracked`.
+                let report_untracked_reads: &[bool] = if input_options.untracked {
+                    &[false, true]
+                } else {
+                    &[false]
+                };
+                for &report_untracked_read in report_untracked_reads {
+                    let accessor = InputTryGetter {
+                        signature: method.sig.clone(),
+                        return_type: getter_ty.clone(),
+                        storage: input_storage.clone(),
+                        report_untracked_read,
+                    };
+                    accessor_trait_methods.push(AccessorKind::Try(accessor));
+
+                    let accessor = InputHas {
+                        signature: method.sig.clone(),
+                        storage: input_storage.clone(),
+                        report_untracked_read,
+                    };
+                    accessor_trait_methods.push(AccessorKind::Has(accessor));
+                }
+
+                for changed_at in [false, true] {
+                    let accessor = InputMetadataGetter {
+                        signature: method.sig.clone(),
+                        storage: input_storage.clone(),
+                        changed_at,
+                    };
+                    accessor_trait_methods.push(AccessorKind::Metadata(accessor));
                 }
             }
+            (QueryKind::Interned, None) => {
+                let interned_struct_path = interned_struct_path.unwrap();
+                let method = Intern {
+                    signature: signature.clone(),
+                    pat_and_tys: pat_and_tys.clone(),
+                    interned_struct_path: interned_struct_path.clone(),
+                };
 
-            _ => (),
+                trait_methods.push(Queries::Intern(method));
+
+                let mut method = Lookup {
+                    signature: signature.clone(),
+                    pat_and_tys: pat_and_tys.clone(),
+                    return_ty: *return_ty,
+                    interned_struct_path,
+                };
+                method.prepare_signature();
+
+                lookup_signatures.push(TraitItem::Fn(make_trait_method(method.signature.clone())));
+                lookup_methods.push(method);
+            }
+            // tracked function without *any* invoke.
+            (QueryKind::Tracked, None) => {
+                let method = TrackedQuery {
+                    trait_name: trait_name_ident.clone(),
+                    generated_struct: Some(GeneratedInputStruct {
+                        input_struct_name: input_struct_name.clone(),
+                        create_data_ident: create_data_ident.clone(),
+                    }),
+                    signature: signature.clone(),
+                    pat_and_tys: pat_and_tys.clone(),
+                    invoke: None,
+                    cycle,
+                    lru,
+                    dependencies,
+                    options: tracked_options.clone(),
+                    return_ref: tracked_return_ref,
+                    hoisted: false,
+                };
+
+                trait_methods.push(Queries::TrackedQuery(method));
+            }
+            // tracked function with an invoke
+            (QueryKind::Tracked, Some(invoke)) => {
+                let method = TrackedQuery {
+                    trait_name: trait_name_ident.clone(),
+                    generated_struct: Some(GeneratedInputStruct {
+                        input_struct_name: input_struct_name.clone(),
+                        create_data_ident: create_data_ident.clone(),
+                    }),
+                    signature: signature.clone(),
+                    pat_and_tys: pat_and_tys.clone(),
+                    invoke: Some(invoke),
+                    cycle,
+                    lru,
+                    dependencies,
+                    options: tracked_options.clone(),
+                    return_ref: tracked_return_ref,
+                    hoisted: false,
+                };
+
+                trait_methods.push(Queries::TrackedQuery(method))
+            }
+            (QueryKind::TrackedWithSalsaStruct, Some(invoke)) => {
+                let method = TrackedQuery {
+                    trait_name: trait_name_ident.clone(),
+                    generated_struct: None,
+                    signature: signature.clone(),
+                    pat_and_tys: pat_and_tys.clone(),
+                    invoke: Some(invoke),
+                    cycle,
+                    lru,
+                    dependencies,
+                    options: tracked_options.clone(),
+                    return_ref: tracked_return_ref,
+                    hoisted: false,
+                };
+
+                trait_methods.push(Queries::TrackedQuery(method))
+            }
+            (QueryKind::TrackedWithSalsaStruct, None) => unreachable!(),
+            (QueryKind::Transparent, None) => {
+                let method = Transparent {
+                    signature: method.sig.clone(),
+                    pat_and_tys: pat_and_tys.clone(),
+                    invoke: None,
+                };
+                trait_methods.push(Queries::Transparent(method));
+            }
+            (QueryKind::Transparent, Some(invoke)) => {
+                let method = Transparent {
+                    signature: method.sig.clone(),
+                    pat_and_tys: pat_and_tys.clone(),
+                    invoke: Some(invoke),
+                };
+                trait_methods.push(Queries::Transparent(method));
+            }
+            (QueryKind::Accumulated, None) => {
+                let AccumulatedOptions { query, accumulator } = accumulated.unwrap();
+                let method = Accumulated {
+                    trait_name: trait_name_ident.clone(),
+                    signature: signature.clone(),
+                    pat_and_tys: pat_and_tys.clone(),
+                    query,
+                    accumulator,
+                    create_data_ident: None,
+                };
+                trait_methods.push(Queries::Accumulated(method));
+            }
+            // error/invalid constructions
+            (QueryKind::Accumulated, Some(path)) => {
+                return Err(syn::Error::new(
+                    path.span(),
+                    "Accumulated queries cannot be used with an `#[invoke]`",
+                ))
+            }
+            (QueryKind::Interned, Some(path)) => {
+                return Err(syn::Error::new(
+                    path.span(),
+                    "Interned queries cannot be used with an `#[invoke]`",
+                ))
+            }
+            (QueryKind::Input, Some(path)) => {
+                return Err(syn::Error::new(
+                    path.span(),
+                    "Inputs cannot be used with an `#[invoke]`",
+                ))
+            }
+        }
+    }
+
+    // `accumulated` has to be called on the target query's shim, so the shim can't
+    // stay hidden inside the target's method.
+    let mut accumulated_targets = vec![];
+    for method in &trait_methods {
+        if let Queries::Accumulated(accumulated) = method {
+            accumulated_targets.push(accumulated.query.clone());
+        }
+    }
+    let mut targets = std::collections::HashMap::new();
+    for method in &mut trait_methods {
+        if let Queries::TrackedQuery(tracked_query) = method {
+            if accumulated_targets.contains(&tracked_query.signature.ident) {
+                tracked_query.hoisted = true;
+                let create_data_ident = tracked_query
+                    .generated_struct
+                    .as_ref()
+                    .map(|generated_struct| generated_struct.create_data_ident.clone());
+                targets.insert(
+                    tracked_query.signature.ident.clone(),
+                    (create_data_ident, param_types(&tracked_query.pat_and_tys)),
+                );
+            }
+        }
+    }
+    for method in &mut trait_methods {
+        if let Queries::Accumulated(accumulated) = method {
+            let Some((create_data_ident, target_params)) = targets.get(&accumulated.query) else {
+                return Err(syn::Error::new(
+                    accumulated.query.span(),
+                    format!(
+                        "`{}` is not a tracked query of this query group",
+                        accumulated.query
+                    ),
+                ));
+            };
+            // the parameters are passed on to the target's shim as they are.
+            let params = param_types(&accumulated.pat_and_tys);
+            if params != *target_params {
+                return Err(syn::Error::new(
+                    accumulated.signature.inputs.span(),
+                    format!(
+                        "`{}` must take the same parameters as `{}`: expected `({})`, found `({})`",
+                        accumulated.signature.ident,
+                        accumulated.query,
+                        target_params.join(", "),
+                        params.join(", "),
+                    ),
+                ));
+            }
+            accumulated.create_data_ident = create_data_ident.clone();
+        }
+    }
+    let hoisted_shims = trait_methods
+        .iter()
+        .filter_map(|method| match method {
+            Queries::TrackedQuery(tracked_query) => tracked_query.hoisted_shim(),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let hoisted_shims = (!hoisted_shims.is_empty()).then(|| {
+        let module = TrackedQuery::shims_module(trait_name_ident);
+        quote! {
+            #[doc(hidden)]
+            mod #module {
+                use super::*;
+
+                #(#hoisted_shims)*
+            }
+        }
+    });
+
+    // the setters call the hook, so whichever impl holds them requires the observer.
+    let mut observer = None;
+    let mut observer_bound = None;
+    if options.observer && !inputs.is_empty() {
+        let observer_trait_name = InputObserver::trait_name(trait_name_ident);
+        observer_bound = Some(quote!(DB: #observer_trait_name,));
+        observer = Some(InputObserver {
+            trait_name: trait_name_ident.clone(),
+            vis: item_trait.vis.clone(),
+            inputs: inputs.clone(),
+        });
+    }
+
+    let load_fns = inputs
+        .iter()
+        .filter_map(InputStorage::load_definition)
+        .collect::<Vec<_>>();
+    let slot_fns = inputs
+        .iter()
+        .filter_map(InputStorage::slot_definition)
+        .collect::<Vec<_>>();
+
+    let mut metadata = None;
+    if !inputs.is_empty() {
+        let inputs_metadata = InputsMetadata {
+            trait_name: trait_name_ident.clone(),
+            inputs: inputs.clone(),
+        };
+        input_struct_fields.push(InputStructField {
+            name: quote!(__metadata),
+            ty: InputsMetadata::struct_name(trait_name_ident).to_token_stream(),
+            init: inputs_metadata.init(),
+            attrs: quote!(),
+        });
+        metadata = Some(inputs_metadata);
+    }
+
+    let mut journal = None;
+    if let Some(capacity) = options.journal.filter(|_| !inputs.is_empty()) {
+        let journal_struct_name = InputsJournal::struct_name(trait_name_ident);
+        input_struct_fields.push(InputStructField {
+            name: quote!(__journal),
+            ty: journal_struct_name.to_token_stream(),
+            init: quote!(#journal_struct_name::default()),
+            attrs: quote!(#[return_ref]),
+        });
+
+        for action in [
+            JournalAction::Undo,
+            JournalAction::Redo,
+            JournalAction::Clear,
+        ] {
+            let inputs_journal = InputsJournal {
+                trait_name: trait_name_ident.clone(),
+                create_data_ident: create_data_ident.clone(),
+                inputs: inputs.clone(),
+                capacity,
+                action,
+            };
+            if action == JournalAction::Undo {
+                journal = Some(inputs_journal.definition());
+            }
+            setter_trait_methods.push(SetterKind::Journal(inputs_journal));
         }
     }
 
     let fields = input_struct_fields
-        .into_iter()
+        .iter()
         .map(|input| {
-            let name = input.name;
-            let ret = input.ty;
-            quote! { #name: Option<#ret> }
+            let name = &input.name;
+            let ty = &input.ty;
+            let attrs = &input.attrs;
+            quote! { #attrs #name: #ty }
         })
         .collect::<Vec<proc_macro2::TokenStream>>();
 
@@ -390,7 +762,9 @@ pub(crate) fn query_group_impl(
         }
     };
 
-    let field_params = std::iter::repeat_n(quote! { None }, fields.len())
+    let field_params = input_struct_fields
+        .iter()
+        .map(|input| input.init.clone())
         .collect::<Vec<proc_macro2::TokenStream>>();
 
     let create_data_method = quote! {
@@ -401,21 +775,85 @@ pub(crate) fn query_group_impl(
         }
     };
 
-    let mut setter_signatures = vec![];
-    let mut setter_methods = vec![];
-    for trait_item in setter_trait_methods
-        .iter()
-        .map(|method| method.to_token_stream())
-        .map(|tokens| syn::parse2::<syn::TraitItemFn>(tokens).unwrap())
-    {
-        let mut methods_sans_body = trait_item.clone();
-        methods_sans_body.default = None;
-        methods_sans_body.semi_token = Some(syn::Token![;](trait_item.span()));
+    let inputs_trait_name = format_ident!("{}Inputs", trait_name_ident);
+
+    let mut batch_update_struct = None;
+    let mut inputs_builder = None;
+    let mut snapshot = None;
+    if !inputs.is_empty() {
+        let batch_update = BatchUpdate {
+            trait_name: trait_name_ident.clone(),
+            vis: item_trait.vis.clone(),
+            create_data_ident: create_data_ident.clone(),
+            input_struct_name: input_struct_name.clone(),
+            inputs,
+        };
+        batch_update_struct = Some(batch_update.definition());
+        inputs_builder = Some(InputsBuilder {
+            trait_name: trait_name_ident.clone(),
+            vis: item_trait.vis.clone(),
+            inputs: batch_update.inputs.clone(),
+            setters_trait: if options.separate_setters {
+                inputs_trait_name.clone()
+            } else {
+                trait_name_ident.clone()
+            },
+            batch_update_method: batch_update.method_name(),
+        });
 
-        setter_signatures.push(TraitItem::Fn(methods_sans_body));
-        setter_methods.push(TraitItem::Fn(trait_item));
+        if options.snapshot {
+            for import in [false, true] {
+                let inputs_snapshot = InputsSnapshot {
+                    trait_name: trait_name_ident.clone(),
+                    vis: item_trait.vis.clone(),
+                    create_data_ident: create_data_ident.clone(),
+                    inputs: batch_update.inputs.clone(),
+                    batch_update_method: batch_update.method_name(),
+                    import,
+                };
+                if !import {
+                    snapshot = Some(inputs_snapshot.definition());
+                }
+                setter_trait_methods.push(SetterKind::Snapshot(inputs_snapshot));
+            }
+        }
+
+        setter_trait_methods.push(SetterKind::Batch(batch_update));
     }
 
+    let setter_trait_methods = setter_trait_methods
+        .iter()
+        .map(|method| method.to_token_stream());
+    let accessor_trait_methods = accessor_trait_methods
+        .iter()
+        .map(|method| method.to_token_stream());
+
+    // with `setters = separate`, the `&mut self` methods go into a `{Trait}Inputs`
+    // extension trait, so that `&dyn Trait` only exposes the read-only ones.
+    let mut inputs_trait = None;
+    let trait_impl_bound = observer_bound.clone().filter(|_| !options.separate_setters);
+    let (mut setter_signatures, setter_methods) = if options.separate_setters {
+        let (input_signatures, input_methods) = split_trait_methods(setter_trait_methods);
+        let vis = &item_trait.vis;
+        inputs_trait = Some(quote! {
+            #vis trait #inputs_trait_name: #trait_name_ident {
+                #(#input_signatures)*
+            }
+
+            impl<DB> #inputs_trait_name for DB
+            where
+                DB: #trait_name_ident,
+                #observer_bound
+            {
+                #(#input_methods)*
+            }
+        });
+
+        split_trait_methods(accessor_trait_methods)
+    } else {
+        split_trait_methods(setter_trait_methods.chain(accessor_trait_methods))
+    };
+
     item_trait.items.append(&mut setter_signatures);
     item_trait.items.append(&mut lookup_signatures);
 
@@ -424,6 +862,7 @@ pub(crate) fn query_group_impl(
         impl<DB> #trait_name_ident for DB
         where
             DB: #supertraits,
+            #trait_impl_bound
         {
             #(#trait_methods)*
 
@@ -439,8 +878,30 @@ pub(crate) fn query_group_impl(
 
         #trait_impl
 
+        #inputs_trait
+
+        #observer
+
         #input_struct
 
+        #(#slot_structs)*
+
+        #(#slot_fns)*
+
+        #(#load_fns)*
+
+        #hoisted_shims
+
+        #batch_update_struct
+
+        #inputs_builder
+
+        #metadata
+
+        #journal
+
+        #snapshot
+
         #create_data_method
     }
     .into();
@@ -448,6 +909,32 @@ pub(crate) fn query_group_impl(
     Ok(out)
 }
 
+/// The parameter types of a query, as strings to compare them.
+fn param_types(pat_and_tys: &[syn::PatType]) -> Vec<String> {
+    pat_and_tys
+        .iter()
+        .map(|pat_type| pat_type.ty.to_token_stream().to_string())
+        .collect()
+}
+
+/// Splits generated methods into the signatures declared in a trait and the
+/// methods of its blanket impl.
+fn split_trait_methods(
+    methods: impl Iterator<Item = proc_macro2::TokenStream>,
+) -> (Vec<TraitItem>, Vec<TraitItem>) {
+    let mut signatures = vec![];
+    let mut bodies = vec![];
+    for trait_item in methods.map(|tokens| syn::parse2::<syn::TraitItemFn>(tokens).unwrap()) {
+        let mut methods_sans_body = trait_item.clone();
+        methods_sans_body.default = None;
+        methods_sans_body.semi_token = Some(syn::Token![;](trait_item.span()));
+
+        signatures.push(TraitItem::Fn(methods_sans_body));
+        bodies.push(TraitItem::Fn(trait_item));
+    }
+    (signatures, bodies)
+}
+
 /// Parenthesis helper
 pub(crate) struct Parenthesized<T>(pub(crate) T);
 
@@ -462,6 +949,320 @@ where
     }
 }
 
+/// Either the path of a recovery function, or a declarative `fallback = expr` or
+/// `error = ctor`.
+impl syn::parse::Parse for Cycle {
+    fn parse(input: syn::parse::ParseStream<'_>) -> syn::Result<Self> {
+        if !(input.peek(syn::Ident) && input.peek2(syn::Token![=])) {
+            return input.parse::<Path>().map(Cycle::Recover);
+        }
+
+        let ident: syn::Ident = input.parse()?;
+        input.parse::<syn::Token![=]>()?;
+        let cycle = match ident.to_string().as_str() {
+            "fallback" => Cycle::Fallback(input.parse()?),
+            "error" => Cycle::Error(input.parse()?),
+            _ => {
+                return Err(syn::Error::new(
+                    ident.span(),
+                    format!("unknown cycle option `{ident}`"),
+                ))
+            }
+        };
+        if !input.is_empty() {
+            return Err(input.error(format!(
+                "`{ident}` cannot be combined with other cycle options"
+            )));
+        }
+        Ok(cycle)
+    }
+}
+
+/// The options of `#[salsa::accumulated(query = q, accumulator = A)]`.
+struct AccumulatedOptions {
+    query: syn::Ident,
+    accumulator: Path,
+}
+
+impl syn::parse::Parse for AccumulatedOptions {
+    fn parse(input: syn::parse::ParseStream<'_>) -> syn::Result<Self> {
+        let mut query = None;
+        let mut accumulator = None;
+
+        while !input.is_empty() {
+            let ident: syn::Ident = input.parse()?;
+            input.parse::<syn::Token![=]>()?;
+            match ident.to_string().as_str() {
+                "query" => query = Some(input.parse::<syn::Ident>()?),
+                "accumulator" => accumulator = Some(input.parse::<Path>()?),
+                _ => {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        format!("unknown accumulated option `{ident}`"),
+                    ))
+                }
+            }
+
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<syn::Token![,]>()?;
+        }
+
+        match (query, accumulator) {
+            (Some(query), Some(accumulator)) => Ok(AccumulatedOptions { query, accumulator }),
+            _ => Err(input.error("expected `query = ...` and `accumulator = ...`")),
+        }
+    }
+}
+
+/// The options of `#[salsa::tracked_options(...)]`, passed through to `#[salsa::tracked]`.
+#[derive(Default)]
+struct TrackedOptions(Vec<syn::Meta>);
+
+impl syn::parse::Parse for TrackedOptions {
+    fn parse(input: syn::parse::ParseStream<'_>) -> syn::Result<Self> {
+        let options =
+            syn::punctuated::Punctuated::<syn::Meta, syn::Token![,]>::parse_terminated(input)?;
+        Ok(TrackedOptions(options.into_iter().collect()))
+    }
+}
+
+/// Rejects `tracked_options` that are given twice, or that another attribute of the
+/// query already passes to `#[salsa::tracked]`, as listed in `generated`.
+fn check_tracked_options(options: &[syn::Meta], generated: &[(&str, bool)]) -> syn::Result<()> {
+    let mut seen = std::collections::HashSet::new();
+    for option in options {
+        let name = option.path().to_token_stream().to_string();
+        if !seen.insert(name.clone()) {
+            return Err(syn::Error::new(
+                option.span(),
+                format!("duplicate tracked option `{name}`"),
+            ));
+        }
+        if generated.contains(&(name.as_str(), true)) {
+            return Err(syn::Error::new(
+                option.span(),
+                format!(
+                    "tracked option `{name}` is already set by another attribute of this query"
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Parses the parenthesized options of an attribute like `#[salsa::input(...)]`,
+/// falling back to the defaults when the attribute has no argument list.
+fn parse_options<T>(tts: TokenStream) -> syn::Result<T>
+where
+    T: syn::parse::Parse + Default,
+{
+    let tts = proc_macro2::TokenStream::from(tts);
+    match tts.clone().into_iter().next() {
+        Some(proc_macro2::TokenTree::Group(group))
+            if group.delimiter() == proc_macro2::Delimiter::Parenthesis =>
+        {
+            syn::parse2::<Parenthesized<T>>(tts).map(|options| options.0)
+        }
+        _ => Ok(T::default()),
+    }
+}
+
+/// How many writes `#[query_group(journal)]` keeps for undoing.
+const DEFAULT_JOURNAL_CAPACITY: usize = 128;
+
+/// Options accepted by `#[query_group(...)]` itself.
+#[derive(Default)]
+struct QueryGroupOptions {
+    /// Records input writes so they can be undone and redone, keeping up to this many,
+    /// from `journal` or `journal = N`.
+    journal: Option<usize>,
+    /// Moves the setters into a `{Trait}Inputs` trait, from `setters = separate`.
+    separate_setters: bool,
+    /// Reports input writes to a `{Trait}InputObserver`, from `observer`.
+    observer: bool,
+    /// Generates `export_{trait}_inputs`/`import_{trait}_inputs`, from `snapshot`.
+    snapshot: bool,
+}
+
+impl syn::parse::Parse for QueryGroupOptions {
+    fn parse(input: syn::parse::ParseStream<'_>) -> syn::Result<Self> {
+        let mut options = QueryGroupOptions::default();
+
+        while !input.is_empty() {
+            let ident: syn::Ident = input.parse()?;
+            match ident.to_string().as_str() {
+                "journal" => {
+                    let capacity = if input.peek(syn::Token![=]) {
+                        input.parse::<syn::Token![=]>()?;
+                        let capacity = input.parse::<syn::LitInt>()?;
+                        match capacity.base10_parse::<usize>()? {
+                            0 => {
+                                return Err(syn::Error::new(
+                                    capacity.span(),
+                                    "the journal capacity must be at least 1",
+                                ))
+                            }
+                            capacity => capacity,
+                        }
+                    } else {
+                        DEFAULT_JOURNAL_CAPACITY
+                    };
+                    options.journal = Some(capacity);
+                }
+                "observer" => options.observer = true,
+                "snapshot" => {
+                    // the snapshot derives serde's traits, which the crate using the
+                    // macro only depends on when it enables the feature.
+                    if !cfg!(feature = "serde") {
+                        return Err(syn::Error::new(
+                            ident.span(),
+                            "`snapshot` requires the `serde` feature of `query-group`",
+                        ));
+                    }
+                    options.snapshot = true;
+                }
+                "setters" => {
+                    input.parse::<syn::Token![=]>()?;
+                    let placement: syn::Ident = input.parse()?;
+                    match placement.to_string().as_str() {
+                        "separate" => options.separate_setters = true,
+                        "inline" => options.separate_setters = false,
+                        _ => {
+                            return Err(syn::Error::new(
+                                placement.span(),
+                                "expected `setters = separate` or `setters = inline`",
+                            ))
+                        }
+                    }
+                }
+                _ => {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        format!("unknown query group option `{ident}`"),
+                    ))
+                }
+            }
+
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<syn::Token![,]>()?;
+        }
+
+        Ok(options)
+    }
+}
+
+/// Options accepted by `#[salsa::input(...)]`.
+#[derive(Default)]
+struct InputOptions {
+    /// The value an input has before it is set, from `default = expr`
+    /// (or `Default::default()` for a bare `default`).
+    default: Option<syn::Expr>,
+    /// The durability used by setters that don't take one, from `durability = HIGH`.
+    durability: Option<proc_macro2::TokenStream>,
+    /// Makes `set_{input}` skip values equal to the stored one, from `skip_unchanged`.
+    skip_unchanged: bool,
+    /// Checks values before they are stored, from `validate = path::to::fn -> Error`.
+    validate: Option<Validator>,
+    /// Makes the getter return `&T` instead of cloning the value, from `return_ref`.
+    return_ref: bool,
+    /// Makes reads of the input not record a dependency, from `untracked`.
+    untracked: bool,
+    /// Computes the value of an input that was never set, from `load = path::to::fn`.
+    ///
+    /// The loaded value is memoized, not stored in the input: `has_{input}` and
+    /// `try_{input}` still treat the input as unset, `export_{trait}_inputs` leaves it
+    /// out and the journal never records it. Only `update_{input}` stores it, as the
+    /// value it starts from.
+    load: Option<Path>,
+}
+
+impl syn::parse::Parse for InputOptions {
+    fn parse(input: syn::parse::ParseStream<'_>) -> syn::Result<Self> {
+        let mut options = InputOptions::default();
+
+        while !input.is_empty() {
+            let ident: syn::Ident = input.parse()?;
+            match ident.to_string().as_str() {
+                "default" => {
+                    let default = if input.peek(syn::Token![=]) {
+                        input.parse::<syn::Token![=]>()?;
+                        input.parse::<syn::Expr>()?
+                    } else {
+                        parse_quote!(Default::default())
+                    };
+                    options.default = Some(default);
+                }
+                "durability" => {
+                    input.parse::<syn::Token![=]>()?;
+                    let durability = input.parse::<syn::Expr>()?;
+                    // allow `durability = HIGH` as a shorthand for `salsa::Durability::HIGH`;
+                    // any other ident is an expression, e.g. a constant.
+                    let durability = match durability {
+                        syn::Expr::Path(path)
+                            if path.path.get_ident().is_some_and(|ident| {
+                                ident == "LOW" || ident == "MEDIUM" || ident == "HIGH"
+                            }) =>
+                        {
+                            quote!(salsa::Durability::#path)
+                        }
+                        durability => durability.to_token_stream(),
+                    };
+                    options.durability = Some(durability);
+                }
+                "skip_unchanged" => options.skip_unchanged = true,
+                "return_ref" => options.return_ref = true,
+                "untracked" => options.untracked = true,
+                "load" => {
+                    input.parse::<syn::Token![=]>()?;
+                    options.load = Some(input.parse::<Path>()?);
+                }
+                "validate" => {
+                    input.parse::<syn::Token![=]>()?;
+                    let path = input.parse::<Path>()?;
+                    // the error type can't be inferred from the path, but `try_set_{input}`
+                    // has to name it; see `Validator::error`.
+                    if !input.peek(syn::Token![->]) {
+                        return Err(input.error("expected `validate = path -> ErrorType`"));
+                    }
+                    input.parse::<syn::Token![->]>()?;
+                    let error = input.parse::<syn::Type>()?;
+                    options.validate = Some(Validator { path, error });
+                }
+                _ => {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        format!("unknown input option `{ident}`"),
+                    ))
+                }
+            }
+
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<syn::Token![,]>()?;
+        }
+
+        Ok(options)
+    }
+}
+
+/// Makes the trait method `method_name` return a reference to `return_ty`
+/// instead of returning it by value.
+fn return_by_ref(item_trait: &mut ItemTrait, method_name: &syn::Ident, return_ty: &syn::Type) {
+    for item in &mut item_trait.items {
+        match item {
+            TraitItem::Fn(item) if item.sig.ident == *method_name => {
+                item.sig.output = parse_quote!(-> &#return_ty);
+            }
+            _ => (),
+        }
+    }
+}
+
 fn make_trait_method(sig: syn::Signature) -> TraitItemFn {
     TraitItemFn {
         attrs: vec![],
@@ -471,16 +1272,84 @@ fn make_trait_method(sig: syn::Signature) -> TraitItemFn {
     }
 }
 
+/// Checks that an input's value type can be stored in the generated `{Trait}Data` struct.
+fn check_input_type(ty: &syn::Type) -> syn::Result<()> {
+    if matches!(ty, syn::Type::Slice(_) | syn::Type::TraitObject(_)) {
+        return Err(syn::Error::new(
+            ty.span(),
+            "inputs must return a sized type; consider wrapping it in a `Box` or `Arc`",
+        ));
+    }
+
+    let mut checker = InputTypeChecker { error: None };
+    checker.visit_type_mut(&mut ty.clone());
+    match checker.error {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+/// Finds the parts of a type that can't be stored in a salsa input: borrows that
+/// aren't `'static`, `impl Trait` and inferred types.
+struct InputTypeChecker {
+    error: Option<syn::Error>,
+}
+
+impl InputTypeChecker {
+    fn report(&mut self, span: Span, message: &str) {
+        if self.error.is_none() {
+            self.error = Some(syn::Error::new(span, message));
+        }
+    }
+}
+
+impl VisitMut for InputTypeChecker {
+    fn visit_type_reference_mut(&mut self, i: &mut syn::TypeReference) {
+        match &i.lifetime {
+            Some(lifetime) if lifetime.ident == "static" => (),
+            _ => self.report(
+                i.span(),
+                "inputs cannot return borrowed data; use a `'static` reference or an owned type",
+            ),
+        }
+        syn::visit_mut::visit_type_reference_mut(self, i);
+    }
+
+    fn visit_generic_argument_mut(&mut self, i: &mut syn::GenericArgument) {
+        match i {
+            syn::GenericArgument::Lifetime(lifetime) if lifetime.ident != "static" => self.report(
+                lifetime.span(),
+                "inputs cannot return borrowed data; use a `'static` lifetime or an owned type",
+            ),
+            _ => syn::visit_mut::visit_generic_argument_mut(self, i),
+        }
+    }
+
+    fn visit_type_impl_trait_mut(&mut self, i: &mut syn::TypeImplTrait) {
+        self.report(i.span(), "inputs cannot return `impl Trait`");
+    }
+
+    fn visit_type_infer_mut(&mut self, i: &mut syn::TypeInfer) {
+        self.report(i.span(), "inputs must spell out their return type");
+    }
+
+    // lifetimes in function pointers and `Fn(..)` bounds are higher-ranked, so they're fine.
+    fn visit_type_bare_fn_mut(&mut self, _: &mut syn::TypeBareFn) {}
+
+    fn visit_parenthesized_generic_arguments_mut(
+        &mut self,
+        _: &mut syn::ParenthesizedGenericArguments,
+    ) {
+    }
+}
+
 struct RemoveAttrsFromTraitMethods;
 
 impl VisitMut for RemoveAttrsFromTraitMethods {
     fn visit_item_trait_mut(&mut self, i: &mut syn::ItemTrait) {
         for item in &mut i.items {
-            match item {
-                TraitItem::Fn(trait_item_fn) => {
-                    trait_item_fn.attrs = vec![];
-                }
-                _ => (),
+            if let TraitItem::Fn(trait_item_fn) = item {
+                trait_item_fn.attrs = vec![];
             }
         }
     }
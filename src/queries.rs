@@ -1,3 +1,4 @@
+use heck::{ToSnakeCase, ToUpperCamelCase};
 use quote::{ToTokens, format_ident, quote};
 use syn::{FnArg, Ident, PatType, Path, Receiver, ReturnType, Type, parse_quote};
 
@@ -6,9 +7,33 @@ pub(crate) struct TrackedQuery {
     pub(crate) signature: syn::Signature,
     pub(crate) pat_and_tys: Vec<PatType>,
     pub(crate) invoke: Option<Path>,
-    pub(crate) cycle: Option<Path>,
+    pub(crate) cycle: Option<Cycle>,
     pub(crate) lru: Option<u32>,
+    /// Ported from old salsa's `#[salsa::dependencies]`: only the dependencies matter,
+    /// so the value is never compared and only the most recent one is kept.
+    pub(crate) dependencies: bool,
+    /// Options passed through to `#[salsa::tracked(...)]`, from `#[salsa::tracked_options(...)]`.
+    pub(crate) options: Vec<syn::Meta>,
+    /// Makes the trait method return a reference to the value memoized by salsa.
+    pub(crate) return_ref: bool,
     pub(crate) generated_struct: Option<GeneratedInputStruct>,
+    /// Whether the shim is defined outside of the trait method, see
+    /// [`TrackedQuery::hoisted_shim`].
+    pub(crate) hoisted: bool,
+}
+
+/// How a tracked query handles cycles, from `#[salsa::cycle(...)]`.
+pub(crate) enum Cycle {
+    /// `#[salsa::cycle(path)]`: a recovery function `path(db, cycle, keys..)` computing
+    /// the value of each query participating in the cycle.
+    Recover(Path),
+    /// `#[salsa::cycle(fallback = expr)]`: every participant recovers with `expr`, which
+    /// is evaluated on its own: it can't use the database or the query's parameters.
+    Fallback(syn::Expr),
+    /// `#[salsa::cycle(error = ctor)]`: every participant recovers with `Err(ctor(names))`,
+    /// where `names` are the names of the participating queries, in the order salsa
+    /// reports them.
+    Error(Path),
 }
 
 pub(crate) struct GeneratedInputStruct {
@@ -16,67 +41,183 @@ pub(crate) struct GeneratedInputStruct {
     pub(crate) create_data_ident: Ident,
 }
 
-impl ToTokens for TrackedQuery {
-    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+impl TrackedQuery {
+    fn shim_ident(&self) -> Ident {
+        format_ident!("{}_shim", self.signature.ident)
+    }
+
+    /// The module holding the hoisted shims of a query group, see
+    /// [`TrackedQuery::hoisted_shim`].
+    pub(crate) fn shims_module(trait_name: &Ident) -> Ident {
+        format_ident!("__{}_shims", trait_name.to_string().to_snake_case())
+    }
+
+    /// The tracked function memoizing the query.
+    fn shim(&self) -> proc_macro2::TokenStream {
         let sig = &self.signature;
         let trait_name = &self.trait_name;
-
         let ret = &sig.output;
+        let shim = self.shim_ident();
+        let vis = self.hoisted.then(|| quote!(pub(super)));
 
         let invoke = match &self.invoke {
             Some(path) => path.to_token_stream(),
             None => sig.ident.to_token_stream(),
         };
 
-        let fn_ident = &sig.ident;
-        let shim: Ident = format_ident!("{}_shim", fn_ident);
-
-        let annotation = match (self.cycle.clone(), self.lru) {
-            (Some(cycle), Some(lru)) => quote!(#[salsa::tracked(lru = #lru, recovery_fn = #cycle)]),
-            (Some(cycle), None) => quote!(#[salsa::tracked(recovery_fn = #cycle)]),
-            (None, Some(lru)) => quote!(#[salsa::tracked(lru = #lru)]),
-            (None, None) => quote!(#[salsa::tracked]),
+        let mut options = vec![];
+        if self.return_ref {
+            options.push(quote!(return_ref));
+        }
+        if self.dependencies {
+            options.push(quote!(no_eq));
+        }
+        if let Some(lru) = self.lru.or(self.dependencies.then_some(1)) {
+            options.push(quote!(lru = #lru));
+        }
+        let cycle_adapters = self
+            .cycle
+            .as_ref()
+            .map(|cycle| self.cycle_adapters(cycle, &mut options));
+        options.extend(self.options.iter().map(ToTokens::to_token_stream));
+        let annotation = if options.is_empty() {
+            quote!(#[salsa::tracked])
+        } else {
+            quote!(#[salsa::tracked(#(#options),*)])
         };
 
+        let pat_and_tys = &self.pat_and_tys;
+        let params = self.pat_and_tys.iter().map(|pat_type| &pat_type.pat);
+        let input = self.generated_struct.as_ref().map(|generated_struct| {
+            let input_struct_name = &generated_struct.input_struct_name;
+            quote!(_input: #input_struct_name,)
+        });
+
+        quote! {
+            #annotation
+            #vis fn #shim(
+                db: &dyn #trait_name,
+                #input
+                #(#pat_and_tys),*
+            ) #ret {
+                #invoke(db, #(#params),*)
+            }
+
+            #cycle_adapters
+        }
+    }
+
+    /// The recovery function adapting the query's cycle handling to the signature
+    /// salsa expects, which includes the `{Trait}Data` parameter of the shim. Adds
+    /// the matching `recovery_fn` option to `options`.
+    fn cycle_adapters(
+        &self,
+        cycle: &Cycle,
+        options: &mut Vec<proc_macro2::TokenStream>,
+    ) -> proc_macro2::TokenStream {
+        let trait_name = &self.trait_name;
+        let shim = self.shim_ident();
+        let ty = match &self.signature.output {
+            ReturnType::Type(_, ty) => ty.to_token_stream(),
+            ReturnType::Default => quote!(()),
+        };
         let pat_and_tys = &self.pat_and_tys;
         let params = self
             .pat_and_tys
             .iter()
-            .map(|pat_type| pat_type.pat.clone())
-            .collect::<Vec<Box<syn::Pat>>>();
-
-        let method = match &self.generated_struct {
-            Some(generated_struct) => {
-                let input_struct_name = &generated_struct.input_struct_name;
-                let create_data_ident = &generated_struct.create_data_ident;
+            .map(|pat_type| &pat_type.pat)
+            .collect::<Vec<_>>();
+        let input = self.generated_struct.as_ref().map(|generated_struct| {
+            let input_struct_name = &generated_struct.input_struct_name;
+            quote!(_input: #input_struct_name,)
+        });
 
-                quote! {
-                    #sig {
-                        #annotation
-                        fn #shim(
-                            db: &dyn #trait_name,
-                            _input: #input_struct_name,
-                            #(#pat_and_tys),*
-                        ) #ret {
-                            #invoke(db, #(#params),*)
-                        }
-                        #shim(self, #create_data_ident(self), #(#params),*)
-                    }
+        let recover_adapter = format_ident!("{}_recover", shim);
+        let recover = |body: proc_macro2::TokenStream| {
+            quote! {
+                #[allow(unused_variables)]
+                fn #recover_adapter(
+                    db: &dyn #trait_name,
+                    cycle: &salsa::Cycle,
+                    #input
+                    #(#pat_and_tys),*
+                ) -> #ty {
+                    #body
                 }
             }
-            None => {
-                quote! {
-                    #sig {
-                        #annotation
-                        fn #shim(
-                            db: &dyn #trait_name,
-                            #(#pat_and_tys),*
-                        ) #ret {
-                            #invoke(db, #(#params),*)
-                        }
-                        #shim(self, #(#params),*)
-                    }
+        };
+
+        options.push(quote!(recovery_fn = #recover_adapter));
+        match cycle {
+            Cycle::Recover(recover_fn) => recover(quote!(#recover_fn(db, cycle, #(#params),*))),
+            // the fallback is evaluated in a function of its own, so it can't see the
+            // adapter's parameters.
+            Cycle::Fallback(fallback) => recover(quote! {
+                fn fallback() -> #ty {
+                    #fallback
                 }
+                fallback()
+            }),
+            // participants are named after their queries rather than their shims.
+            Cycle::Error(error) => recover(quote! {
+                Err(#error(
+                    cycle
+                        .participant_keys()
+                        .map(|key| {
+                            let name =
+                                salsa::Database::ingredient_debug_name(db, key.ingredient_index());
+                            let name: &str = &name;
+                            name.strip_suffix("_shim").unwrap_or(name).to_string()
+                        })
+                        .collect(),
+                ))
+            }),
+        }
+    }
+
+    /// The arguments the shim is called with from a method of the blanket impl.
+    fn shim_args(&self) -> proc_macro2::TokenStream {
+        let params = self.pat_and_tys.iter().map(|pat_type| &pat_type.pat);
+        let input = self.generated_struct.as_ref().map(|generated_struct| {
+            let create_data_ident = &generated_struct.create_data_ident;
+            quote!(#create_data_ident(self),)
+        });
+        quote!(self, #input #(#params),*)
+    }
+
+    /// The shim of a hoisted query, which is defined in the query group's
+    /// `__{trait}_shims` module instead of inside the trait method so that it can be
+    /// named. The module keeps shims of queries with the same name in different query
+    /// groups apart.
+    pub(crate) fn hoisted_shim(&self) -> Option<proc_macro2::TokenStream> {
+        self.hoisted.then(|| self.shim())
+    }
+}
+
+impl ToTokens for TrackedQuery {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let sig = &mut self.signature.clone();
+
+        // the shim returns the value, salsa hands out a reference to it.
+        if self.return_ref {
+            if let ReturnType::Type(_, ty) = &self.signature.output {
+                sig.output = parse_quote!(-> &#ty);
+            }
+        }
+
+        let shim = self.shim_ident();
+        let (definition, shim) = if self.hoisted {
+            let module = Self::shims_module(&self.trait_name);
+            (None, quote!(#module::#shim))
+        } else {
+            (Some(self.shim()), shim.to_token_stream())
+        };
+        let args = self.shim_args();
+
+        let method = quote! {
+            #sig {
+                #definition
+                #shim(#args)
             }
         };
 
@@ -84,21 +225,561 @@ impl ToTokens for TrackedQuery {
     }
 }
 
-pub(crate) struct InputQuery {
+/// A method returning the values pushed to an accumulator by a tracked query and
+/// the queries it called, from `#[salsa::accumulated(query = q, accumulator = A)]`.
+///
+/// The target query's shim is hoisted out of its method so that its
+/// `accumulated` function can be called.
+pub(crate) struct Accumulated {
+    pub(crate) trait_name: Ident,
     pub(crate) signature: syn::Signature,
-    pub(crate) create_data_ident: Ident,
+    pub(crate) pat_and_tys: Vec<PatType>,
+    pub(crate) query: Ident,
+    pub(crate) accumulator: Path,
+    /// The `create_data_{Trait}` function, if the target query's shim takes the `{Trait}Data`.
+    pub(crate) create_data_ident: Option<Ident>,
 }
 
-impl ToTokens for InputQuery {
+impl ToTokens for Accumulated {
     fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
         let sig = &self.signature;
-        let fn_ident = &sig.ident;
+        let accumulator = &self.accumulator;
+        let module = TrackedQuery::shims_module(&self.trait_name);
+        let shim = format_ident!("{}_shim", self.query);
+        let input = self
+            .create_data_ident
+            .as_ref()
+            .map(|create_data_ident| quote!(#create_data_ident(self),));
+        let params = self.pat_and_tys.iter().map(|pat_type| &pat_type.pat);
+
+        let method = quote! {
+            #sig {
+                #module::#shim::accumulated::<#accumulator>(self, #input #(#params),*)
+            }
+        };
+        method.to_tokens(tokens);
+    }
+}
+
+/// An input's validator, from `#[salsa::input(validate = path -> Error)]`.
+#[derive(Clone)]
+pub(crate) struct Validator {
+    /// A `fn(&T) -> Result<(), Error>` run on every value before it is stored.
+    pub(crate) path: Path,
+    /// The validator's error type, returned by `try_set_{input}`.
+    ///
+    /// It has to be written out: the trait declares `try_set_{input}`, and a trait
+    /// signature can't name the return type of a function from its path, since the
+    /// type of a function item can't be written and a blanket impl over
+    /// `Fn(&T) -> Result<(), E>` leaves `E` unconstrained.
+    pub(crate) error: Type,
+}
+
+/// Describes where an input's value lives in the generated `{Trait}Data` struct.
+///
+/// Inputs without parameters are stored directly as an `Option<T>` field. Inputs
+/// with parameters (keyed inputs) are stored as a map from the key to a per-key
+/// salsa input (a "slot"). Setting a key that already has a slot only invalidates
+/// the queries that read that key. Setting a new key adds its slot to the map in
+/// place; queries look up their slot through a memoized tracked function, so the
+/// lookups of the other keys still find the same slot and salsa backdates them
+/// instead of re-executing their callers.
+#[derive(Clone)]
+pub(crate) struct InputStorage {
+    pub(crate) trait_name: Ident,
+    pub(crate) field: Ident,
+    /// The position of the input's field in the `{Trait}Data` struct.
+    pub(crate) index: usize,
+    /// The type of the input's value, i.e. the getter's return type.
+    pub(crate) ty: Type,
+    pub(crate) keys: Vec<PatType>,
+    pub(crate) slot_struct_name: Ident,
+    pub(crate) create_data_ident: Ident,
+    /// The durability used by setters that don't take one explicitly.
+    pub(crate) durability: Option<proc_macro2::TokenStream>,
+    /// The value the getter falls back to when the input was never set.
+    pub(crate) default: Option<syn::Expr>,
+    /// Whether the plain setter skips writes that wouldn't change the value.
+    pub(crate) skip_unchanged: bool,
+    /// Run on every value before it is stored.
+    pub(crate) validate: Option<Validator>,
+    /// The `{Trait}JournalEntry` enum, when writes are recorded for undo/redo.
+    pub(crate) journal: Option<Ident>,
+    /// Whether the value is kept in salsa storage and only borrowed by the getters.
+    pub(crate) return_ref: bool,
+    /// Whether the value lives behind a lock in the `{Trait}Data` field instead of
+    /// in the field itself, so that reading it doesn't record a dependency.
+    pub(crate) untracked: bool,
+    /// A `fn(&dyn Trait, keys..) -> T` computing the value of an input that was never set.
+    pub(crate) load: Option<Path>,
+    /// Whether writes are reported to the `{Trait}InputObserver`.
+    pub(crate) observe: bool,
+}
+
+impl InputStorage {
+    pub(crate) fn is_keyed(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    /// The name of the enum variants generated for this input.
+    pub(crate) fn variant(&self) -> Ident {
+        format_ident!("{}", self.field.to_string().to_upper_camel_case())
+    }
+
+    /// The panic message used when an input is read before being set.
+    pub(crate) fn unset_message(&self) -> String {
+        format!(
+            "{}::{} was read before being set",
+            self.trait_name, self.field
+        )
+    }
+
+    /// Turns `value` (an `Option<T>`, or an `Option<&T>` for `return_ref` inputs
+    /// unless `owned` is set) into a `T`, falling back to the default or the loader
+    /// and panicking if the input has neither.
+    pub(crate) fn unwrap_value(
+        &self,
+        value: &proc_macro2::TokenStream,
+        owned: bool,
+    ) -> proc_macro2::TokenStream {
+        if self.load.is_some() {
+            let load_ident = self.load_ident();
+            let create_data_ident = &self.create_data_ident;
+            let pats = self.keys.iter().map(|pat_type| &pat_type.pat);
+            let clone = (self.return_ref && owned).then(|| quote!(.clone()));
+            return quote! {
+                #value.unwrap_or_else(|| {
+                    #load_ident(self, #create_data_ident(self), #(#pats.clone()),*)#clone
+                })
+            };
+        }
+
+        match &self.default {
+            Some(default) => quote!(#value.unwrap_or_else(|| #default)),
+            None => {
+                let message = self.unset_message();
+                quote!(#value.expect(#message))
+            }
+        }
+    }
+
+    fn load_ident(&self) -> Ident {
+        format_ident!("load_{}_{}", self.field, self.trait_name)
+    }
+
+    /// The tracked function memoizing the loader of a `load` input, so that it only
+    /// runs once for each key until the input is set or the loader's inputs change.
+    pub(crate) fn load_definition(&self) -> Option<proc_macro2::TokenStream> {
+        let load = self.load.as_ref()?;
+        let load_ident = self.load_ident();
+        let trait_name = &self.trait_name;
+        let input_struct_name = format_ident!("{}Data", trait_name);
+        let ty = &self.ty;
+        let keys = &self.keys;
+        let pats = self.keys.iter().map(|pat_type| &pat_type.pat);
+        let annotation = if self.return_ref {
+            quote!(#[salsa::tracked(return_ref)])
+        } else {
+            quote!(#[salsa::tracked])
+        };
+
+        Some(quote! {
+            #[allow(non_snake_case)]
+            #annotation
+            fn #load_ident(
+                db: &dyn #trait_name,
+                _input: #input_struct_name,
+                #(#keys),*
+            ) -> #ty {
+                #load(db, #(#pats),*)
+            }
+        })
+    }
+
+    fn slot_ident(&self) -> Ident {
+        format_ident!("slot_{}_{}", self.field, self.trait_name)
+    }
+
+    /// The tracked function looking up the slot of a key of a keyed input, which is
+    /// what queries depend on instead of the whole map.
+    pub(crate) fn slot_definition(&self) -> Option<proc_macro2::TokenStream> {
+        if !self.is_keyed() {
+            return None;
+        }
+        let slot_ident = self.slot_ident();
+        let trait_name = &self.trait_name;
+        let input_struct_name = format_ident!("{}Data", trait_name);
+        let slot_struct_name = &self.slot_struct_name;
+        let field = &self.field;
+        let keys = &self.keys;
+        let key = self.key();
+
+        Some(quote! {
+            #[allow(non_snake_case)]
+            #[salsa::tracked]
+            fn #slot_ident(
+                db: &dyn #trait_name,
+                data: #input_struct_name,
+                #(#keys),*
+            ) -> Option<#slot_struct_name> {
+                data.#field(db).get(&#key).copied()
+            }
+        })
+    }
+
+    fn key(&self) -> proc_macro2::TokenStream {
+        let pats = self.keys.iter().map(|pat_type| &pat_type.pat);
+        quote!((#(#pats.clone(),)*))
+    }
+
+    /// Binds the key tuple of a keyed input to `key`, as expected by [`InputStorage::store`].
+    pub(crate) fn bind_key(&self) -> Option<proc_macro2::TokenStream> {
+        self.is_keyed().then(|| {
+            let key = self.key();
+            quote!(let key = #key;)
+        })
+    }
+
+    /// The type of the key used for the slot map of a keyed input.
+    pub(crate) fn key_ty(&self) -> proc_macro2::TokenStream {
+        let tys = self.keys.iter().map(|pat_type| &pat_type.ty);
+        quote!((#(#tys,)*))
+    }
+
+    /// An expression evaluating to the currently stored `Option<T>`, or to an
+    /// `Option<&T>` for `return_ref` inputs.
+    pub(crate) fn read(&self) -> proc_macro2::TokenStream {
+        let field = &self.field;
+        let create_data_ident = &self.create_data_ident;
+        let as_ref = self.return_ref.then(|| quote!(.as_ref()));
+
+        if self.untracked {
+            return quote! {
+                #create_data_ident(self)
+                    .#field(self)
+                    .read()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner)
+                    .clone()
+            };
+        }
+
+        if !self.is_keyed() {
+            return quote! {
+                #create_data_ident(self).#field(self)#as_ref
+            };
+        }
+
+        let slot_ident = self.slot_ident();
+        let pats = self.keys.iter().map(|pat_type| &pat_type.pat);
+        quote! {
+            match #slot_ident(self, #create_data_ident(self), #(#pats.clone()),*) {
+                Some(slot) => slot.value(self)#as_ref,
+                None => None,
+            }
+        }
+    }
+
+    /// Statements storing `value` (an `Option<T>`), optionally with an explicit durability.
+    pub(crate) fn write(
+        &self,
+        value: &proc_macro2::TokenStream,
+        durability: Option<&proc_macro2::TokenStream>,
+    ) -> proc_macro2::TokenStream {
         let create_data_ident = &self.create_data_ident;
+        let store = self.store(value, durability);
+
+        let key = self.bind_key();
+
+        quote! {
+            let data = #create_data_ident(self);
+            #key
+            #store
+        }
+    }
+
+    /// Statements panicking when the input's validator rejects `value` (a `T`).
+    pub(crate) fn assert_valid(
+        &self,
+        value: &proc_macro2::TokenStream,
+    ) -> Option<proc_macro2::TokenStream> {
+        let validate = &self.validate.as_ref()?.path;
+        let message = format!("invalid value for `{}::{}`", self.trait_name, self.field);
+        Some(quote! {
+            if let Err(error) = #validate(&#value) {
+                panic!("{}: {}", #message, error);
+            }
+        })
+    }
+
+    /// Returns early from a setter with `ret` when `value` is equal to the stored value.
+    pub(crate) fn return_if_unchanged(
+        &self,
+        value: &proc_macro2::TokenStream,
+        ret: &proc_macro2::TokenStream,
+    ) -> proc_macro2::TokenStream {
+        let read = self.read();
+        let current = if self.return_ref {
+            quote!(current)
+        } else {
+            quote!(current.as_ref())
+        };
+        quote! {
+            let current = #read;
+            if #current == Some(&#value) {
+                #ret;
+            }
+        }
+    }
+
+    /// The `T: PartialEq` bound required by [`InputStorage::return_if_unchanged`].
+    ///
+    /// The bound is higher-ranked so that rustc doesn't reject it as a trivially
+    /// false bound for value types without `PartialEq`; for those, the method
+    /// simply can't be called.
+    pub(crate) fn partial_eq_bound(&self) -> syn::WhereClause {
+        let ty = &self.ty;
+        parse_quote!(where for<'__trivial_bounds> #ty: PartialEq)
+    }
+
+    /// Statements recording the durability and revision of a write in the
+    /// `{Trait}InputsMetadata`, in the revision of the write, binding the durability
+    /// to `recorded_durability` when it's reported to the observer.
+    fn record_metadata(
+        &self,
+        durability: Option<&proc_macro2::TokenStream>,
+    ) -> proc_macro2::TokenStream {
+        // without an explicit durability, salsa keeps the one the value already had.
+        let recorded_durability = match durability {
+            Some(durability) => quote!(#durability),
+            None if self.is_keyed() => quote! {
+                field.get(&key).map_or(salsa::Durability::LOW, |(durability, _)| *durability)
+            },
+            None => quote!(field.0),
+        };
+        let assign = if self.is_keyed() {
+            quote!(field.insert(key.clone(), (recorded_durability, changed_at));)
+        } else {
+            quote!(*field = (recorded_durability, changed_at);)
+        };
+        let update = update_field(
+            &InputsMetadata::struct_name(&self.trait_name),
+            &quote!(metadata),
+            self.index,
+            &quote!(None),
+            &quote! {
+                let recorded_durability = #recorded_durability;
+                #assign
+                recorded_durability
+            },
+        );
+        let bind = self.observe.then(|| quote!(let recorded_durability =));
+
+        quote! {
+            #bind {
+                let metadata = data.__metadata(self);
+                let zalsa = salsa::plumbing::ZalsaDatabase::zalsa_mut(self);
+                let changed_at = zalsa.current_revision();
+                #update
+            };
+        }
+    }
+
+    /// Records a write of the input: its metadata, its journal entry and the observer
+    /// notification. Expects the `{Trait}Data` to be bound to `data`, for keyed inputs
+    /// the key tuple to be bound to `key` and, with a journal, the replaced value to be
+    /// bound to `previous`.
+    pub(crate) fn record(
+        &self,
+        durability: Option<&proc_macro2::TokenStream>,
+    ) -> proc_macro2::TokenStream {
+        let journal = self.journal.as_ref().map(|journal_entry| {
+            let variant = self.variant();
+            let entry = if self.is_keyed() {
+                quote!(#journal_entry::#variant(key, previous))
+            } else {
+                quote!(#journal_entry::#variant(previous))
+            };
+            quote!(data.__journal(self).record(#entry);)
+        });
+
+        let metadata = self.record_metadata(durability);
+        let notify = self.observe.then(|| {
+            let observer = InputObserver::trait_name(&self.trait_name);
+            let hook = InputObserver::hook_name(&self.trait_name);
+            let input_enum = InputObserver::enum_name(&self.trait_name);
+            let variant = self.variant();
+            quote!(#observer::#hook(self, #input_enum::#variant, recorded_durability);)
+        });
+        quote! {
+            #metadata
+            #journal
+            #notify
+        }
+    }
+
+    /// The pattern binding the replaced value for [`InputStorage::record`], which only
+    /// uses it with a journal.
+    pub(crate) fn previous_pat(&self) -> proc_macro2::TokenStream {
+        if self.journal.is_some() {
+            quote!(previous)
+        } else {
+            quote!(_)
+        }
+    }
+
+    /// Like [`InputStorage::write`], but expects the `{Trait}Data` to be bound to `data`
+    /// and, for keyed inputs, the key tuple to be bound to `key`.
+    pub(crate) fn store(
+        &self,
+        value: &proc_macro2::TokenStream,
+        durability: Option<&proc_macro2::TokenStream>,
+    ) -> proc_macro2::TokenStream {
+        let field = &self.field;
+        let setter_ident = format_ident!("set_{}", field);
+
+        let with_durability = durability.map(|durability| quote!(.with_durability(#durability)));
+
+        // the setter hands back the value it replaced, which is what the journal records.
+        let previous = self.journal.is_some().then(|| quote!(let previous =));
+        let record = self.record(durability);
+
+        if self.untracked {
+            // the field itself never changes; a synthetic write starts a new revision so
+            // that queries which reported an untracked read get re-executed. it comes
+            // first, so the value is never replaced while the old revision is still current.
+            let previous = previous.unwrap_or_else(|| quote!(let _ =));
+            let durability = durability
+                .or(self.durability.as_ref())
+                .map_or_else(|| quote!(salsa::Durability::LOW), Clone::clone);
+            return quote! {
+                salsa::Database::synthetic_write(self, #durability);
+                #previous std::mem::replace(
+                    &mut *data
+                        .#field(self)
+                        .write()
+                        .unwrap_or_else(std::sync::PoisonError::into_inner),
+                    #value,
+                );
+                #record
+            };
+        }
+
+        if !self.is_keyed() {
+            return quote! {
+                use salsa::Setter;
+                #previous data.#setter_ident(self)
+                    #with_durability
+                    .to(#value);
+                #record
+            };
+        }
+
+        // a new key gets its slot and its value in a single revision, and the slot is
+        // added to the map in place instead of writing a copy of the map.
+        let slot_struct_name = &self.slot_struct_name;
+        let input_struct_name = format_ident!("{}Data", self.trait_name);
+        let set_durability = match durability {
+            Some(durability) => quote!(Some(#durability)),
+            None => quote!(None),
+        };
+        let insert = update_field(
+            &input_struct_name,
+            &quote!(data),
+            self.index,
+            &set_durability,
+            &quote!(field.insert(key.clone(), slot)),
+        );
+        let replace = replace_field(slot_struct_name, &quote!(slot), 0, &set_durability, value);
+        quote! {
+            use salsa::Setter;
+            let existing = data.#field(self).get(&key).copied();
+            #previous match existing {
+                Some(slot) => slot.set_value(self)
+                    #with_durability
+                    .to(#value),
+                None => {
+                    let slot = #slot_struct_name::new(self, None);
+                    let zalsa = salsa::plumbing::ZalsaDatabase::zalsa_mut(self);
+                    zalsa.new_revision();
+                    #insert;
+                    #replace
+                }
+            };
+            #record
+        }
+    }
+}
+
+/// An expression updating field `index` of the salsa input `id` in place, through the
+/// ingredient of `input_struct`: `update` is evaluated with the field bound to `field`
+/// as a `&mut`, and is what the expression evaluates to. Expects `zalsa` to be bound
+/// to the `&mut Zalsa` of a revision that was already started.
+fn update_field(
+    input_struct: &Ident,
+    id: &proc_macro2::TokenStream,
+    index: usize,
+    durability: &proc_macro2::TokenStream,
+    update: &proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let field = syn::Index::from(index);
+    quote! {{
+        let index = zalsa.add_or_lookup_jar_by_type(
+            &<salsa::plumbing::input::JarImpl<#input_struct>>::default(),
+        );
+        let (ingredient, runtime) = zalsa.lookup_ingredient_mut(index);
+        ingredient
+            .assert_type_mut::<salsa::plumbing::input::IngredientImpl<#input_struct>>()
+            .set_field(runtime, #id, #index, #durability, |fields| {
+                let field = &mut fields.#field;
+                #update
+            })
+    }}
+}
+
+/// Like [`update_field`], but replacing the field with `value` and evaluating to the
+/// replaced value.
+fn replace_field(
+    input_struct: &Ident,
+    id: &proc_macro2::TokenStream,
+    index: usize,
+    durability: &proc_macro2::TokenStream,
+    value: &proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    update_field(
+        input_struct,
+        id,
+        index,
+        durability,
+        &quote!(std::mem::replace(field, #value)),
+    )
+}
+
+pub(crate) struct InputQuery {
+    pub(crate) signature: syn::Signature,
+    pub(crate) storage: InputStorage,
+    /// Generates `{input}_tracked` for an untracked input, which reports an untracked
+    /// read to salsa: the calling query is re-executed in every new revision, whether
+    /// or not the input changed.
+    pub(crate) report_untracked_read: bool,
+}
+
+impl ToTokens for InputQuery {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let sig = &mut self.signature.clone();
+        let read = self.storage.read();
+
+        let value = self.storage.unwrap_value(&quote!(value), false);
+
+        let report = self.report_untracked_read.then(|| {
+            sig.ident = format_ident!("{}_tracked", sig.ident);
+            quote!(salsa::Database::report_untracked_read(self);)
+        });
 
         let method = quote! {
             #sig {
-                let data = #create_data_ident(self);
-                data.#fn_ident(self).unwrap()
+                #report
+                let value = #read;
+                #value
             }
         };
         method.to_tokens(tokens);
@@ -108,7 +789,10 @@ impl ToTokens for InputQuery {
 pub(crate) struct InputSetter {
     pub(crate) signature: syn::Signature,
     pub(crate) return_type: syn::Type,
-    pub(crate) create_data_ident: Ident,
+    pub(crate) storage: InputStorage,
+    /// Generates `set_{input}_if_changed`, which skips writing a value equal to the
+    /// stored one, instead of `set_{input}`.
+    pub(crate) if_changed: bool,
 }
 
 impl ToTokens for InputSetter {
@@ -117,9 +801,12 @@ impl ToTokens for InputSetter {
 
         let ty = &self.return_type;
         let fn_ident = &sig.ident;
-        let create_data_ident = &self.create_data_ident;
 
-        let setter_ident = format_ident!("set_{}", fn_ident);
+        let setter_ident = if self.if_changed {
+            format_ident!("set_{}_if_changed", fn_ident)
+        } else {
+            format_ident!("set_{}", fn_ident)
+        };
         sig.ident = setter_ident.clone();
 
         let value_argument: PatType = parse_quote!(__value: #ty);
@@ -127,19 +814,28 @@ impl ToTokens for InputSetter {
 
         // make `&self` `&mut self` instead.
         let mut_recevier: Receiver = parse_quote!(&mut self);
-        sig.inputs
-            .first_mut()
-            .map(|og| *og = FnArg::Receiver(mut_recevier));
+        if let Some(og) = sig.inputs.first_mut() {
+            *og = FnArg::Receiver(mut_recevier);
+        }
 
         // remove the return value.
         sig.output = ReturnType::Default;
 
         let value = &value_argument.pat;
+        let skip = (self.if_changed || self.storage.skip_unchanged).then(|| {
+            sig.generics.where_clause = Some(self.storage.partial_eq_bound());
+            self.storage
+                .return_if_unchanged(&value.to_token_stream(), &quote!(return))
+        });
+        let validate = self.storage.assert_valid(&value.to_token_stream());
+        let write = self
+            .storage
+            .write(&quote!(Some(#value)), self.storage.durability.as_ref());
         let method = quote! {
             #sig {
-                use salsa::Setter;
-                let data = #create_data_ident(self);
-                data.#setter_ident(self).to(Some(#value));
+                #validate
+                #skip
+                #write
             }
         };
         method.to_tokens(tokens);
@@ -149,7 +845,7 @@ impl ToTokens for InputSetter {
 pub(crate) struct InputSetterWithDurability {
     pub(crate) signature: syn::Signature,
     pub(crate) return_type: syn::Type,
-    pub(crate) create_data_ident: Ident,
+    pub(crate) storage: InputStorage,
 }
 
 impl ToTokens for InputSetterWithDurability {
@@ -158,9 +854,6 @@ impl ToTokens for InputSetterWithDurability {
 
         let ty = &self.return_type;
         let fn_ident = &sig.ident;
-        let setter_ident = format_ident!("set_{}", fn_ident);
-
-        let create_data_ident = &self.create_data_ident;
 
         sig.ident = format_ident!("set_{}_with_durability", fn_ident);
 
@@ -172,106 +865,1518 @@ impl ToTokens for InputSetterWithDurability {
 
         // make `&self` `&mut self` instead.
         let mut_recevier: Receiver = parse_quote!(&mut self);
-        sig.inputs
-            .first_mut()
-            .map(|og| *og = FnArg::Receiver(mut_recevier));
+        if let Some(og) = sig.inputs.first_mut() {
+            *og = FnArg::Receiver(mut_recevier);
+        }
 
         // remove the return value.
         sig.output = ReturnType::Default;
 
         let value = &value_argument.pat;
         let durability = &durability_argument.pat;
+        let validate = self.storage.assert_valid(&value.to_token_stream());
+        let write = self
+            .storage
+            .write(&quote!(Some(#value)), Some(&durability.to_token_stream()));
         let method = quote! {
             #sig {
-                use salsa::Setter;
-                let data = #create_data_ident(self);
-                data.#setter_ident(self)
-                    .with_durability(#durability)
-                    .to(Some(#value));
+                #validate
+                #write
             }
         };
         method.to_tokens(tokens);
     }
 }
 
-pub(crate) enum SetterKind {
-    Plain(InputSetter),
-    WithDurability(InputSetterWithDurability),
+/// `try_set_{input}`, generated for inputs with a validator: returns the validator's
+/// error instead of panicking, and only stores valid values.
+pub(crate) struct InputTrySetter {
+    pub(crate) signature: syn::Signature,
+    pub(crate) storage: InputStorage,
+    pub(crate) validate: Validator,
 }
 
-impl ToTokens for SetterKind {
+impl ToTokens for InputTrySetter {
     fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
-        match self {
-            SetterKind::Plain(input_setter) => input_setter.to_tokens(tokens),
-            SetterKind::WithDurability(input_setter_with_durability) => {
-                input_setter_with_durability.to_tokens(tokens)
-            }
-        }
-    }
-}
+        let sig = &mut self.signature.clone();
 
-pub(crate) struct Transparent {
-    pub(crate) signature: syn::Signature,
-    pub(crate) pat_and_tys: Vec<PatType>,
-    pub(crate) invoke: Option<Path>,
-}
+        let ty = &self.storage.ty;
+        sig.ident = format_ident!("try_set_{}", sig.ident);
 
-impl ToTokens for Transparent {
-    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
-        let sig = &self.signature;
+        let value_argument: PatType = parse_quote!(__value: #ty);
+        sig.inputs.push(FnArg::Typed(value_argument.clone()));
 
-        let ty = self
-            .pat_and_tys
-            .iter()
-            .map(|pat_type| pat_type.pat.clone())
-            .collect::<Vec<Box<syn::Pat>>>();
+        // make `&self` `&mut self` instead.
+        let mut_recevier: Receiver = parse_quote!(&mut self);
+        if let Some(og) = sig.inputs.first_mut() {
+            *og = FnArg::Receiver(mut_recevier);
+        }
 
-        let invoke = match &self.invoke {
-            Some(path) => path.to_token_stream(),
-            None => sig.ident.to_token_stream(),
-        };
+        let error = &self.validate.error;
+        sig.output = parse_quote!(-> Result<(), #error>);
 
+        let value = &value_argument.pat;
+        let validate = &self.validate.path;
+        // like `set_{input}`, skip writing a valid value equal to the stored one.
+        let skip = self.storage.skip_unchanged.then(|| {
+            sig.generics.where_clause = Some(self.storage.partial_eq_bound());
+            self.storage
+                .return_if_unchanged(&value.to_token_stream(), &quote!(return Ok(())))
+        });
+        let write = self
+            .storage
+            .write(&quote!(Some(#value)), self.storage.durability.as_ref());
         let method = quote! {
             #sig {
-                #invoke(self, #(#ty),*)
+                #validate(&#value)?;
+                #skip
+                #write
+                Ok(())
             }
         };
-
         method.to_tokens(tokens);
     }
 }
-pub(crate) struct Intern {
+
+pub(crate) struct InputUnset {
     pub(crate) signature: syn::Signature,
-    pub(crate) pat_and_tys: Vec<PatType>,
-    pub(crate) interned_struct_path: Path,
+    pub(crate) storage: InputStorage,
 }
 
-impl ToTokens for Intern {
+impl ToTokens for InputUnset {
     fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
-        let sig = &self.signature;
+        let sig = &mut self.signature.clone();
 
-        let ty = self
-            .pat_and_tys
-            .iter()
-            .map(|pat_type| pat_type.clone())
-            .collect::<Vec<syn::PatType>>();
+        sig.ident = format_ident!("unset_{}", sig.ident);
 
-        let interned_pat = ty.iter().next().unwrap();
-        let interned_pat = &interned_pat.pat;
+        // make `&self` `&mut self` instead.
+        let mut_recevier: Receiver = parse_quote!(&mut self);
+        if let Some(og) = sig.inputs.first_mut() {
+            *og = FnArg::Receiver(mut_recevier);
+        }
 
-        let wrapper_struct = self.interned_struct_path.to_token_stream();
+        // remove the return value.
+        sig.output = ReturnType::Default;
 
+        let create_data_ident = &self.storage.create_data_ident;
+        let field = &self.storage.field;
+        let key = self.storage.bind_key();
+        // unsetting an input that isn't set would start a new revision for nothing, and
+        // a key that was never set has no slot, which would have to be created.
+        let return_if_absent = if self.storage.is_keyed() {
+            quote! {
+                if !data.#field(self).contains_key(&key) {
+                    return;
+                }
+            }
+        } else {
+            let read = self.storage.read();
+            quote! {
+                if #read.is_none() {
+                    return;
+                }
+            }
+        };
+        let store = self
+            .storage
+            .store(&quote!(None), self.storage.durability.as_ref());
         let method = quote! {
             #sig {
-                #wrapper_struct::new(self, #interned_pat)
+                let data = #create_data_ident(self);
+                #key
+                #return_if_absent
+                #store
             }
         };
-
         method.to_tokens(tokens);
     }
 }
 
-pub(crate) struct Lookup {
+/// `update_{input}` (or `update_{input}_with_durability`), which lets a closure modify
+/// the stored value in place, within a single revision.
+pub(crate) struct InputUpdate {
+    pub(crate) signature: syn::Signature,
+    pub(crate) storage: InputStorage,
+    pub(crate) with_durability: bool,
+}
+
+impl ToTokens for InputUpdate {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let sig = &mut self.signature.clone();
+
+        let ty = &self.storage.ty;
+        let fn_ident = &sig.ident;
+        sig.ident = if self.with_durability {
+            format_ident!("update_{}_with_durability", fn_ident)
+        } else {
+            format_ident!("update_{}", fn_ident)
+        };
+
+        sig.inputs.push(parse_quote!(f: impl FnOnce(&mut #ty)));
+
+        let durability = if self.with_durability {
+            sig.inputs.push(parse_quote!(durability: salsa::Durability));
+            Some(quote!(durability))
+        } else {
+            self.storage.durability.clone()
+        };
+
+        // make `&self` `&mut self` instead.
+        let mut_recevier: Receiver = parse_quote!(&mut self);
+        if let Some(og) = sig.inputs.first_mut() {
+            *og = FnArg::Receiver(mut_recevier);
+        }
+
+        // remove the return value.
+        sig.output = ReturnType::Default;
+
+        // the closure is generic, so keep this method out of the trait object.
+        sig.generics.where_clause = Some(parse_quote!(where Self: Sized));
+
+        let create_data_ident = &self.storage.create_data_ident;
+        let field = &self.storage.field;
+        let key = self.storage.bind_key();
+
+        // `f` runs on the stored value itself, so nothing is copied, and a panic in `f`
+        // or in the validator keeps whatever `f` did so far. only the journal needs a
+        // copy of the value, to undo the update. an unset value is handed back with `f`,
+        // to be replaced by the fallback in the same revision.
+        let (previous, no_previous) = if self.storage.journal.is_some() {
+            (quote!(field.clone()), quote!(None))
+        } else {
+            (quote!(()), quote!(()))
+        };
+        let validate = self.storage.assert_valid(&quote!(*value));
+        let in_place = quote! {
+            let previous = #previous;
+            match field {
+                Some(value) => {
+                    f(value);
+                    #validate
+                    (previous, None)
+                }
+                None => (previous, Some(f)),
+            }
+        };
+        let previous_pat = self.storage.previous_pat();
+
+        let fallback = self.storage.unwrap_value(&quote!(None), true);
+        let validate = self.storage.assert_valid(&quote!(value));
+        let set_durability = match &durability {
+            Some(durability) => quote!(Some(#durability)),
+            None => quote!(None),
+        };
+        let input_struct_name = format_ident!("{}Data", self.storage.trait_name);
+        let slot_struct_name = &self.storage.slot_struct_name;
+        let index = self.storage.index;
+
+        let (update, replace) = if self.storage.untracked {
+            // like `store`, the synthetic write comes before the value changes.
+            let durability = durability
+                .clone()
+                .unwrap_or_else(|| quote!(salsa::Durability::LOW));
+            let lock = quote! {
+                data.#field(self)
+                    .write()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner)
+            };
+            (
+                quote! {
+                    salsa::Database::synthetic_write(self, #durability);
+                    let (#previous_pat, f) = {
+                        let mut field = #lock;
+                        let field = &mut *field;
+                        #in_place
+                    };
+                },
+                quote!(*#lock = Some(value);),
+            )
+        } else if self.storage.is_keyed() {
+            let update = update_field(
+                slot_struct_name,
+                &quote!(slot),
+                0,
+                &set_durability,
+                &in_place,
+            );
+            let insert = update_field(
+                &input_struct_name,
+                &quote!(data),
+                index,
+                &set_durability,
+                &quote!(field.insert(key.clone(), slot)),
+            );
+            let replace = replace_field(
+                slot_struct_name,
+                &quote!(slot),
+                0,
+                &set_durability,
+                &quote!(Some(value)),
+            );
+            (
+                quote! {
+                    let existing = data.#field(self).get(&key).copied();
+                    let zalsa = salsa::plumbing::ZalsaDatabase::zalsa_mut(self);
+                    zalsa.new_revision();
+                    let (#previous_pat, f) = match existing {
+                        Some(slot) => #update,
+                        None => (#no_previous, Some(f)),
+                    };
+                },
+                quote! {
+                    let slot = match existing {
+                        Some(slot) => slot,
+                        None => {
+                            let slot = #slot_struct_name::new(self, None);
+                            let zalsa = salsa::plumbing::ZalsaDatabase::zalsa_mut(self);
+                            #insert;
+                            slot
+                        }
+                    };
+                    let zalsa = salsa::plumbing::ZalsaDatabase::zalsa_mut(self);
+                    #replace;
+                },
+            )
+        } else {
+            let update = update_field(
+                &input_struct_name,
+                &quote!(data),
+                index,
+                &set_durability,
+                &in_place,
+            );
+            let replace = replace_field(
+                &input_struct_name,
+                &quote!(data),
+                index,
+                &set_durability,
+                &quote!(Some(value)),
+            );
+            (
+                quote! {
+                    let zalsa = salsa::plumbing::ZalsaDatabase::zalsa_mut(self);
+                    zalsa.new_revision();
+                    let (#previous_pat, f) = #update;
+                },
+                quote! {
+                    let zalsa = salsa::plumbing::ZalsaDatabase::zalsa_mut(self);
+                    #replace;
+                },
+            )
+        };
+        let record = self.storage.record(durability.as_ref());
+
+        let method = quote! {
+            #sig {
+                let data = #create_data_ident(self);
+                #key
+                #update
+                if let Some(f) = f {
+                    let mut value: #ty = #fallback;
+                    f(&mut value);
+                    #validate
+                    #replace
+                }
+                #record
+            }
+        };
+        method.to_tokens(tokens);
+    }
+}
+
+pub(crate) enum SetterKind {
+    Plain(InputSetter),
+    WithDurability(InputSetterWithDurability),
+    Unset(InputUnset),
+    Update(InputUpdate),
+    TrySet(InputTrySetter),
+    Batch(BatchUpdate),
+    Journal(InputsJournal),
+    Snapshot(InputsSnapshot),
+}
+
+impl ToTokens for SetterKind {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        match self {
+            SetterKind::Plain(input_setter) => input_setter.to_tokens(tokens),
+            SetterKind::WithDurability(input_setter_with_durability) => {
+                input_setter_with_durability.to_tokens(tokens)
+            }
+            SetterKind::Unset(input_unset) => input_unset.to_tokens(tokens),
+            SetterKind::Update(input_update) => input_update.to_tokens(tokens),
+            SetterKind::TrySet(input_try_setter) => input_try_setter.to_tokens(tokens),
+            SetterKind::Batch(batch_update) => batch_update.to_tokens(tokens),
+            SetterKind::Journal(inputs_journal) => inputs_journal.to_tokens(tokens),
+            SetterKind::Snapshot(inputs_snapshot) => inputs_snapshot.to_tokens(tokens),
+        }
+    }
+}
+
+pub(crate) struct InputTryGetter {
+    pub(crate) signature: syn::Signature,
+    pub(crate) return_type: syn::Type,
+    pub(crate) storage: InputStorage,
+    /// Generates `try_{input}_tracked` for an untracked input, like
+    /// [`InputQuery::report_untracked_read`].
+    pub(crate) report_untracked_read: bool,
+}
+
+impl ToTokens for InputTryGetter {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let sig = &mut self.signature.clone();
+
+        let ty = &self.return_type;
+        sig.ident = if self.report_untracked_read {
+            format_ident!("try_{}_tracked", sig.ident)
+        } else {
+            format_ident!("try_{}", sig.ident)
+        };
+        sig.output = parse_quote!(-> Option<#ty>);
+
+        let report = self
+            .report_untracked_read
+            .then(|| quote!(salsa::Database::report_untracked_read(self);));
+        let read = self.storage.read();
+        let method = quote! {
+            #sig {
+                #report
+                #read
+            }
+        };
+        method.to_tokens(tokens);
+    }
+}
+
+/// `has_{input}`, which calls `try_{input}` so that both agree on whether an
+/// input is set.
+pub(crate) struct InputHas {
+    pub(crate) signature: syn::Signature,
+    pub(crate) storage: InputStorage,
+    /// Generates `has_{input}_tracked`, calling `try_{input}_tracked`.
+    pub(crate) report_untracked_read: bool,
+}
+
+impl ToTokens for InputHas {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let sig = &mut self.signature.clone();
+
+        let (try_getter, has) = if self.report_untracked_read {
+            (
+                format_ident!("try_{}_tracked", sig.ident),
+                format_ident!("has_{}_tracked", sig.ident),
+            )
+        } else {
+            (
+                format_ident!("try_{}", sig.ident),
+                format_ident!("has_{}", sig.ident),
+            )
+        };
+        sig.ident = has;
+        sig.output = parse_quote!(-> bool);
+
+        let pats = self.storage.keys.iter().map(|pat_type| &pat_type.pat);
+        let method = quote! {
+            #sig {
+                self.#try_getter(#(#pats),*).is_some()
+            }
+        };
+        method.to_tokens(tokens);
+    }
+}
+
+/// `{input}_durability` and `{input}_changed_at`, reading what the last write of
+/// an input recorded in the `{Trait}InputsMetadata`. For inputs that were never
+/// written, they return `Durability::LOW` and the revision the `{Trait}Data` was
+/// created in, the revision since which the input has been unset.
+pub(crate) struct InputMetadataGetter {
+    pub(crate) signature: syn::Signature,
+    pub(crate) storage: InputStorage,
+    /// Generates `{input}_changed_at` instead of `{input}_durability`.
+    pub(crate) changed_at: bool,
+}
+
+impl ToTokens for InputMetadataGetter {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let sig = &mut self.signature.clone();
+
+        let field = &self.storage.field;
+        let create_data_ident = &self.storage.create_data_ident;
+        let (suffix, ty, index, unwritten) = if self.changed_at {
+            (
+                "changed_at",
+                quote!(salsa::Revision),
+                syn::Index::from(1),
+                quote!(metadata.__created_at(self)),
+            )
+        } else {
+            (
+                "durability",
+                quote!(salsa::Durability),
+                syn::Index::from(0),
+                quote!(salsa::Durability::LOW),
+            )
+        };
+        sig.ident = format_ident!("{}_{}", sig.ident, suffix);
+        sig.output = parse_quote!(-> #ty);
+
+        let body = if self.storage.is_keyed() {
+            let key = self.storage.key();
+            quote! {
+                metadata
+                    .#field(self)
+                    .get(&#key)
+                    .map_or_else(|| #unwritten, |entry| entry.#index)
+            }
+        } else {
+            quote!(metadata.#field(self).#index)
+        };
+
+        let method = quote! {
+            #sig {
+                let metadata = #create_data_ident(self).__metadata(self);
+                #body
+            }
+        };
+        method.to_tokens(tokens);
+    }
+}
+
+// the variants are built once per input and only turned into tokens, so their size
+// doesn't matter.
+#[allow(clippy::large_enum_variant)]
+pub(crate) enum AccessorKind {
+    Try(InputTryGetter),
+    Has(InputHas),
+    Tracked(InputQuery),
+    Metadata(InputMetadataGetter),
+}
+
+impl ToTokens for AccessorKind {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        match self {
+            AccessorKind::Try(input_try_getter) => input_try_getter.to_tokens(tokens),
+            AccessorKind::Has(input_has) => input_has.to_tokens(tokens),
+            AccessorKind::Tracked(input_query) => input_query.to_tokens(tokens),
+            AccessorKind::Metadata(input_metadata_getter) => {
+                input_metadata_getter.to_tokens(tokens)
+            }
+        }
+    }
+}
+
+/// `update_{trait}_inputs`, which applies a batch of input changes collected in a
+/// generated `{Trait}InputsUpdate` struct at once.
+pub(crate) struct BatchUpdate {
+    pub(crate) trait_name: Ident,
+    pub(crate) vis: syn::Visibility,
+    pub(crate) create_data_ident: Ident,
+    pub(crate) input_struct_name: Ident,
+    pub(crate) inputs: Vec<InputStorage>,
+}
+
+impl BatchUpdate {
+    pub(crate) fn struct_name(&self) -> Ident {
+        format_ident!("{}InputsUpdate", self.trait_name)
+    }
+
+    pub(crate) fn method_name(&self) -> Ident {
+        format_ident!(
+            "update_{}_inputs",
+            self.trait_name.to_string().to_snake_case()
+        )
+    }
+
+    /// The `{Trait}InputsUpdate` struct, with methods recording the new value of each
+    /// input, or that it's unset.
+    pub(crate) fn definition(&self) -> proc_macro2::TokenStream {
+        let vis = &self.vis;
+        let struct_name = self.struct_name();
+
+        let mut fields = vec![];
+        let mut methods = vec![];
+        for input in &self.inputs {
+            let field = &input.field;
+            let with_durability = format_ident!("{}_with_durability", field);
+            let unset = format_ident!("unset_{}", field);
+            let ty = &input.ty;
+            let keys = &input.keys;
+            let declared_durability = match &input.durability {
+                Some(durability) => quote!(Some(#durability)),
+                None => quote!(None),
+            };
+            let validate = input.assert_valid(&quote!(__value));
+
+            if input.is_keyed() {
+                let key_ty = input.key_ty();
+                let key = keys.iter().map(|pat_type| &pat_type.pat);
+                let key = quote!((#(#key,)*));
+                fields.push(quote! {
+                    #field: Vec<(#key_ty, Option<#ty>, Option<salsa::Durability>)>
+                });
+                methods.push(quote! {
+                    #vis fn #field(&mut self, #(#keys,)* __value: #ty) -> &mut Self {
+                        #validate
+                        self.#field.push((#key, Some(__value), #declared_durability));
+                        self
+                    }
+
+                    #vis fn #with_durability(
+                        &mut self,
+                        #(#keys,)*
+                        __value: #ty,
+                        durability: salsa::Durability,
+                    ) -> &mut Self {
+                        #validate
+                        self.#field.push((#key, Some(__value), Some(durability)));
+                        self
+                    }
+
+                    #vis fn #unset(&mut self, #(#keys),*) -> &mut Self {
+                        self.#field.push((#key, None, #declared_durability));
+                        self
+                    }
+                });
+            } else {
+                fields.push(quote! {
+                    #field: Option<(Option<#ty>, Option<salsa::Durability>)>
+                });
+                methods.push(quote! {
+                    #vis fn #field(&mut self, __value: #ty) -> &mut Self {
+                        #validate
+                        self.#field = Some((Some(__value), #declared_durability));
+                        self
+                    }
+
+                    #vis fn #with_durability(
+                        &mut self,
+                        __value: #ty,
+                        durability: salsa::Durability,
+                    ) -> &mut Self {
+                        #validate
+                        self.#field = Some((Some(__value), Some(durability)));
+                        self
+                    }
+
+                    #vis fn #unset(&mut self) -> &mut Self {
+                        self.#field = Some((None, #declared_durability));
+                        self
+                    }
+                });
+            }
+        }
+
+        quote! {
+            #[derive(Default)]
+            #vis struct #struct_name {
+                #(#fields),*
+            }
+
+            impl #struct_name {
+                #(#methods)*
+            }
+        }
+    }
+}
+
+impl ToTokens for BatchUpdate {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let struct_name = self.struct_name();
+        let method_name = self.method_name();
+        let create_data_ident = &self.create_data_ident;
+        let input_struct_name = &self.input_struct_name;
+
+        // every value is written in a single new revision, so a query reading several
+        // of the inputs is invalidated once. the slots of new keys are created before
+        // that revision and added to the maps in place, and the bookkeeping of `store`
+        // happens once all values are in.
+        let mut prepares = vec![];
+        let mut writes = vec![];
+        let mut untracked_writes = vec![];
+        let mut records = vec![];
+        for input in &self.inputs {
+            let field = &input.field;
+            let previous = input.previous_pat();
+            let record_with_durability = input.record(Some(&quote!(durability)));
+            let record_without_durability = input.record(None);
+            let record = quote! {
+                match durability {
+                    Some(durability) => { #record_with_durability }
+                    None => { #record_without_durability }
+                }
+            };
+
+            if input.untracked {
+                untracked_writes.push(quote! {
+                    let #field = update.#field.map(|(value, durability)| {
+                        let previous = std::mem::replace(
+                            &mut *data
+                                .#field(self)
+                                .write()
+                                .unwrap_or_else(std::sync::PoisonError::into_inner),
+                            value,
+                        );
+                        (previous, durability)
+                    });
+                });
+                records.push(quote! {
+                    if let Some((#previous, durability)) = #field {
+                        #record
+                    }
+                });
+            } else if input.is_keyed() {
+                let slot_struct_name = &input.slot_struct_name;
+                let key_ty = input.key_ty();
+                let slots = format_ident!("{}_slots", field);
+                prepares.push(quote! {
+                    let mut #field = vec![];
+                    let mut #slots: Option<(
+                        std::collections::HashMap<#key_ty, #slot_struct_name>,
+                        Option<salsa::Durability>,
+                    )> = None;
+                    for (key, value, durability) in update.#field {
+                        let new_slot = #slots
+                            .as_ref()
+                            .and_then(|(slots, _)| slots.get(&key).copied());
+                        let slot = match new_slot.or_else(|| data.#field(self).get(&key).copied()) {
+                            Some(slot) => slot,
+                            // a key that was never set has nothing to unset.
+                            None if value.is_none() => continue,
+                            None => {
+                                let slot = #slot_struct_name::new(self, None);
+                                let (slots, slots_durability) =
+                                    #slots.get_or_insert_with(Default::default);
+                                slots.insert(key.clone(), slot);
+                                *slots_durability = durability;
+                                slot
+                            }
+                        };
+                        #field.push((key, slot, value, durability));
+                    }
+                });
+                let set_slots = update_field(
+                    input_struct_name,
+                    &quote!(data),
+                    input.index,
+                    &quote!(durability),
+                    &quote!(field.extend(slots)),
+                );
+                let set_value = replace_field(
+                    slot_struct_name,
+                    &quote!(slot),
+                    0,
+                    &quote!(durability),
+                    &quote!(value),
+                );
+                writes.push(quote! {
+                    if let Some((slots, durability)) = #slots {
+                        #set_slots;
+                    }
+                    let #field = #field
+                        .into_iter()
+                        .map(|(key, slot, value, durability)| (key, #set_value, durability))
+                        .collect::<Vec<_>>();
+                });
+                records.push(quote! {
+                    for (key, #previous, durability) in #field {
+                        #record
+                    }
+                });
+            } else {
+                let set_value = replace_field(
+                    input_struct_name,
+                    &quote!(data),
+                    input.index,
+                    &quote!(durability),
+                    &quote!(value),
+                );
+                writes.push(quote! {
+                    let #field = update
+                        .#field
+                        .map(|(value, durability)| (#set_value, durability));
+                });
+                records.push(quote! {
+                    if let Some((#previous, durability)) = #field {
+                        #record
+                    }
+                });
+            }
+        }
+
+        let method = quote! {
+            fn #method_name(&mut self, f: impl FnOnce(&mut #struct_name))
+            where
+                Self: Sized,
+            {
+                let mut update = #struct_name::default();
+                f(&mut update);
+
+                let data = #create_data_ident(self);
+                #(#prepares)*
+                let zalsa = salsa::plumbing::ZalsaDatabase::zalsa_mut(self);
+                zalsa.new_revision();
+                #(#writes)*
+                #(#untracked_writes)*
+                #(#records)*
+            }
+        };
+        method.to_tokens(tokens);
+    }
+}
+
+/// `{Trait}InputsBuilder`, which collects a value for every input and writes them
+/// with a single `update_{trait}_inputs` call.
+///
+/// Each required input (one without parameters, `default` or `load`) gets a type
+/// parameter that is `()` until the input is provided and its value type afterwards.
+/// `build_into` is only implemented once all of them are provided, so a database
+/// built this way can't panic on an unset input.
+///
+/// ```
+/// use query_group::query_group;
+///
+/// #[query_group]
+/// pub trait SourceDatabase: salsa::Database {
+///     #[salsa::input]
+///     fn crate_name(&self) -> String;
+///
+///     #[salsa::input(default = 4)]
+///     fn tab_width(&self) -> u32;
+/// }
+///
+/// fn main() {
+///     let mut db = salsa::DatabaseImpl::new();
+///     SourceDatabaseInputsBuilder::new()
+///         .crate_name(String::from("hello"))
+///         .build_into(&mut db);
+/// }
+/// ```
+///
+/// Leaving out `crate_name` doesn't compile:
+///
+/// ```compile_fail
+/// use query_group::query_group;
+///
+/// #[query_group]
+/// pub trait SourceDatabase: salsa::Database {
+///     #[salsa::input]
+///     fn crate_name(&self) -> String;
+///
+///     #[salsa::input(default = 4)]
+///     fn tab_width(&self) -> u32;
+/// }
+///
+/// fn main() {
+///     let mut db = salsa::DatabaseImpl::new();
+///     SourceDatabaseInputsBuilder::new()
+///         .tab_width(2)
+///         .build_into(&mut db);
+/// }
+/// ```
+pub(crate) struct InputsBuilder {
+    pub(crate) trait_name: Ident,
+    pub(crate) vis: syn::Visibility,
+    pub(crate) inputs: Vec<InputStorage>,
+    /// The trait declaring `update_{trait}_inputs`.
+    pub(crate) setters_trait: Ident,
+    pub(crate) batch_update_method: Ident,
+}
+
+impl InputsBuilder {
+    fn is_required(input: &InputStorage) -> bool {
+        !input.is_keyed() && input.default.is_none() && input.load.is_none()
+    }
+}
+
+impl ToTokens for InputsBuilder {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let vis = &self.vis;
+        let struct_name = format_ident!("{}InputsBuilder", self.trait_name);
+        let setters_trait = &self.setters_trait;
+        let batch_update_method = &self.batch_update_method;
+
+        let required = self
+            .inputs
+            .iter()
+            .filter(|input| Self::is_required(input))
+            .map(|input| {
+                let param = format_ident!("{}State", input.variant());
+                (input.field.clone(), param, input.ty.clone())
+            })
+            .collect::<Vec<_>>();
+        let params = required
+            .iter()
+            .map(|(_, param, _)| param)
+            .collect::<Vec<_>>();
+        let unset = required.iter().map(|_| quote!(())).collect::<Vec<_>>();
+        let provided = required.iter().map(|(_, _, ty)| ty).collect::<Vec<_>>();
+
+        let mut fields = vec![];
+        let mut empty = vec![];
+        let mut methods = vec![];
+        let mut applies = vec![];
+        for input in &self.inputs {
+            let field = &input.field;
+            let ty = &input.ty;
+
+            if Self::is_required(input) {
+                let (_, param, _) = required.iter().find(|(name, ..)| name == field).unwrap();
+                fields.push(quote!(#field: #param));
+                empty.push(quote!(#field: ()));
+                applies.push(quote!(update.#field(self.#field);));
+
+                // providing the input changes its type parameter, so the other fields
+                // have to be moved over one by one.
+                let result_params = required.iter().map(|(name, param, ty)| {
+                    if name == field {
+                        quote!(#ty)
+                    } else {
+                        quote!(#param)
+                    }
+                });
+                let moves = self.inputs.iter().map(|other| {
+                    let other = &other.field;
+                    if other == field {
+                        quote!(#other: __value)
+                    } else {
+                        quote!(#other: self.#other)
+                    }
+                });
+                methods.push(quote! {
+                    #vis fn #field(self, __value: #ty) -> #struct_name<#(#result_params),*> {
+                        #struct_name {
+                            #(#moves),*
+                        }
+                    }
+                });
+            } else if input.is_keyed() {
+                let key_ty = input.key_ty();
+                let keys = &input.keys;
+                let pats = input
+                    .keys
+                    .iter()
+                    .map(|pat_type| &pat_type.pat)
+                    .collect::<Vec<_>>();
+                fields.push(quote!(#field: Vec<(#key_ty, #ty)>));
+                empty.push(quote!(#field: Vec::new()));
+                applies.push(quote! {
+                    for ((#(#pats,)*), __value) in self.#field {
+                        update.#field(#(#pats,)* __value);
+                    }
+                });
+                methods.push(quote! {
+                    #vis fn #field(mut self, #(#keys,)* __value: #ty) -> Self {
+                        self.#field.push(((#(#pats,)*), __value));
+                        self
+                    }
+                });
+            } else {
+                fields.push(quote!(#field: Option<#ty>));
+                empty.push(quote!(#field: None));
+                applies.push(quote! {
+                    if let Some(__value) = self.#field {
+                        update.#field(__value);
+                    }
+                });
+                methods.push(quote! {
+                    #vis fn #field(mut self, __value: #ty) -> Self {
+                        self.#field = Some(__value);
+                        self
+                    }
+                });
+            }
+        }
+
+        quote! {
+            #vis struct #struct_name<#(#params),*> {
+                #(#fields),*
+            }
+
+            impl Default for #struct_name<#(#unset),*> {
+                fn default() -> Self {
+                    #struct_name {
+                        #(#empty),*
+                    }
+                }
+            }
+
+            impl #struct_name<#(#unset),*> {
+                #vis fn new() -> Self {
+                    Self::default()
+                }
+            }
+
+            impl<#(#params),*> #struct_name<#(#params),*> {
+                #(#methods)*
+            }
+
+            impl #struct_name<#(#provided),*> {
+                /// Writes every collected value to `db` at once.
+                #vis fn build_into<DB: #setters_trait>(self, db: &mut DB) {
+                    db.#batch_update_method(|update| {
+                        #(#applies)*
+                    });
+                }
+            }
+        }
+        .to_tokens(tokens);
+    }
+}
+
+/// The durability and revision of the last write of each input, kept in a
+/// `{Trait}InputsMetadata` salsa input since salsa doesn't expose them. The
+/// `__metadata` field of the generated `{Trait}Data` struct points to it.
+///
+/// Its fields are in the same order as the inputs' fields in `{Trait}Data`, so
+/// [`InputStorage::index`] is also the index of an input's metadata. Writes record
+/// the metadata in their own revision, so queries reading it are invalidated like
+/// queries reading the values. Inputs and keys that were never written report a
+/// `LOW` durability and the revision the `{Trait}Data` was created in.
+pub(crate) struct InputsMetadata {
+    pub(crate) trait_name: Ident,
+    pub(crate) inputs: Vec<InputStorage>,
+}
+
+impl InputsMetadata {
+    pub(crate) fn struct_name(trait_name: &Ident) -> Ident {
+        format_ident!("{}InputsMetadata", trait_name)
+    }
+
+    /// An expression creating the `{Trait}InputsMetadata`, within `create_data_{Trait}`.
+    pub(crate) fn init(&self) -> proc_macro2::TokenStream {
+        let metadata = Self::struct_name(&self.trait_name);
+        let fields = self.inputs.iter().map(|input| {
+            if input.is_keyed() {
+                quote!(std::collections::HashMap::default())
+            } else {
+                quote!((salsa::Durability::LOW, created_at))
+            }
+        });
+
+        quote! {{
+            let created_at = salsa::plumbing::ZalsaDatabase::zalsa(db).current_revision();
+            #metadata::new(db, #(#fields,)* created_at)
+        }}
+    }
+}
+
+impl ToTokens for InputsMetadata {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let metadata = Self::struct_name(&self.trait_name);
+
+        let fields = self.inputs.iter().map(|input| {
+            let field = &input.field;
+            if input.is_keyed() {
+                let key_ty = input.key_ty();
+                quote! {
+                    #[return_ref]
+                    #field: std::collections::HashMap<#key_ty, (salsa::Durability, salsa::Revision)>
+                }
+            } else {
+                quote!(#field: (salsa::Durability, salsa::Revision))
+            }
+        });
+
+        quote! {
+            #[salsa::input]
+            pub(crate) struct #metadata {
+                #(#fields,)*
+                __created_at: salsa::Revision,
+            }
+        }
+        .to_tokens(tokens);
+    }
+}
+
+/// The undo/redo journal enabled by `#[query_group(journal)]`.
+///
+/// Every input write records the value it replaced as a `{Trait}JournalEntry` in a
+/// `__journal` field of the generated `{Trait}Data` struct. `undo_{trait}_inputs` and
+/// `redo_{trait}_inputs` restore those values through the regular setters. Only the
+/// last `capacity` writes can be undone, and `clear_{trait}_journal` forgets all of them.
+pub(crate) struct InputsJournal {
+    pub(crate) trait_name: Ident,
+    pub(crate) create_data_ident: Ident,
+    pub(crate) inputs: Vec<InputStorage>,
+    /// How many writes the journal keeps, from `journal = N`.
+    pub(crate) capacity: usize,
+    pub(crate) action: JournalAction,
+}
+
+/// The journal method generated by an [`InputsJournal`].
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum JournalAction {
+    /// `undo_{trait}_inputs`
+    Undo,
+    /// `redo_{trait}_inputs`
+    Redo,
+    /// `clear_{trait}_journal`
+    Clear,
+}
+
+impl InputsJournal {
+    pub(crate) fn struct_name(trait_name: &Ident) -> Ident {
+        format_ident!("{}Journal", trait_name)
+    }
+
+    pub(crate) fn entry_name(trait_name: &Ident) -> Ident {
+        format_ident!("{}JournalEntry", trait_name)
+    }
+
+    /// The journal itself and the entries it records.
+    pub(crate) fn definition(&self) -> proc_macro2::TokenStream {
+        let journal = Self::struct_name(&self.trait_name);
+        let journal_entry = Self::entry_name(&self.trait_name);
+        let journal_name = journal.to_string();
+
+        let journal_state = format_ident!("{}State", journal);
+        let capacity = self.capacity;
+        let variants = self.inputs.iter().map(|input| {
+            let variant = input.variant();
+            let ty = &input.ty;
+            if input.is_keyed() {
+                let key_ty = input.key_ty();
+                quote!(#variant(#key_ty, Option<#ty>))
+            } else {
+                quote!(#variant(Option<#ty>))
+            }
+        });
+
+        quote! {
+            pub(crate) enum #journal_entry {
+                #(#variants),*
+            }
+
+            #[derive(Default)]
+            pub(crate) struct #journal_state {
+                undo: std::collections::VecDeque<#journal_entry>,
+                redo: Vec<#journal_entry>,
+            }
+
+            #[derive(Clone, Default)]
+            pub(crate) struct #journal(std::sync::Arc<std::sync::Mutex<#journal_state>>);
+
+            impl #journal {
+                fn state(&self) -> std::sync::MutexGuard<'_, #journal_state> {
+                    self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+                }
+
+                const CAPACITY: usize = #capacity;
+
+                /// Records the value replaced by a write, forgetting everything that could be
+                /// redone and, past the capacity, the oldest write.
+                fn record(&self, entry: #journal_entry) {
+                    let mut state = self.state();
+                    if state.undo.len() == Self::CAPACITY {
+                        state.undo.pop_front();
+                    }
+                    state.undo.push_back(entry);
+                    state.redo.clear();
+                }
+            }
+
+            impl std::fmt::Debug for #journal {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    f.debug_struct(#journal_name).finish_non_exhaustive()
+                }
+            }
+        }
+    }
+
+    pub(crate) fn method_name(&self) -> Ident {
+        let trait_name = self.trait_name.to_string().to_snake_case();
+        match self.action {
+            JournalAction::Undo => format_ident!("undo_{}_inputs", trait_name),
+            JournalAction::Redo => format_ident!("redo_{}_inputs", trait_name),
+            JournalAction::Clear => format_ident!("clear_{}_journal", trait_name),
+        }
+    }
+}
+
+impl ToTokens for InputsJournal {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let method_name = self.method_name();
+        let create_data_ident = &self.create_data_ident;
+        let journal_entry = Self::entry_name(&self.trait_name);
+
+        if self.action == JournalAction::Clear {
+            quote! {
+                fn #method_name(&mut self) {
+                    let journal = #create_data_ident(self).__journal(self).clone();
+                    let mut state = journal.state();
+                    state.undo.clear();
+                    state.redo.clear();
+                }
+            }
+            .to_tokens(tokens);
+            return;
+        }
+
+        let arms = self.inputs.iter().map(|input| {
+            let variant = input.variant();
+            let setter = format_ident!("set_{}", input.field);
+            let unset = format_ident!("unset_{}", input.field);
+            if input.is_keyed() {
+                let keys = (0..input.keys.len())
+                    .map(|index| format_ident!("__key{}", index))
+                    .collect::<Vec<_>>();
+                quote! {
+                    #journal_entry::#variant((#(#keys,)*), Some(value)) => self.#setter(#(#keys,)* value),
+                    #journal_entry::#variant((#(#keys,)*), None) => self.#unset(#(#keys),*),
+                }
+            } else {
+                quote! {
+                    #journal_entry::#variant(Some(value)) => self.#setter(value),
+                    #journal_entry::#variant(None) => self.#unset(),
+                }
+            }
+        });
+
+        // restoring goes through the regular setters, which record the value they
+        // replace on the undo stack and clear the redo stack. Undo moves that record
+        // over to the redo stack; both put the remaining redo stack back afterwards.
+        let (pop, depth, finish) = if self.action == JournalAction::Redo {
+            (
+                quote!(state.redo.pop()),
+                None,
+                quote! {
+                    journal.state().redo = redo;
+                },
+            )
+        } else {
+            (
+                quote!(state.undo.pop_back()),
+                Some(quote!(let depth = journal.state().undo.len();)),
+                quote! {
+                    let mut state = journal.state();
+                    let replaced = if state.undo.len() > depth {
+                        state.undo.pop_back()
+                    } else {
+                        None
+                    };
+                    state.redo = redo;
+                    state.redo.extend(replaced);
+                },
+            )
+        };
+
+        let method = quote! {
+            fn #method_name(&mut self) -> bool {
+                let journal = #create_data_ident(self).__journal(self).clone();
+                let (entry, redo) = {
+                    let mut state = journal.state();
+                    let Some(entry) = #pop else {
+                        return false;
+                    };
+                    (entry, std::mem::take(&mut state.redo))
+                };
+                #depth
+
+                match entry {
+                    #(#arms)*
+                }
+
+                #finish
+                true
+            }
+        };
+        method.to_tokens(tokens);
+    }
+}
+
+/// `export_{trait}_inputs` and `import_{trait}_inputs`, enabled by
+/// `#[query_group(snapshot)]`.
+///
+/// The `{Trait}InputsSnapshot` mirrors the fields of `{Trait}Data`: an `Option<T>` per
+/// input, or a list of the keys that were set along with their values for keyed inputs.
+/// Importing is a single `update_{trait}_inputs` call, so validation and durabilities
+/// still apply and the imported values are written in one revision.
+pub(crate) struct InputsSnapshot {
+    pub(crate) trait_name: Ident,
+    pub(crate) vis: syn::Visibility,
+    pub(crate) create_data_ident: Ident,
+    pub(crate) inputs: Vec<InputStorage>,
+    pub(crate) batch_update_method: Ident,
+    /// Generates `import_{trait}_inputs` instead of `export_{trait}_inputs`.
+    pub(crate) import: bool,
+}
+
+impl InputsSnapshot {
+    fn struct_name(&self) -> Ident {
+        format_ident!("{}InputsSnapshot", self.trait_name)
+    }
+
+    pub(crate) fn method_name(&self) -> Ident {
+        let action = if self.import { "import" } else { "export" };
+        format_ident!(
+            "{}_{}_inputs",
+            action,
+            self.trait_name.to_string().to_snake_case()
+        )
+    }
+
+    /// The `{Trait}InputsSnapshot` struct.
+    pub(crate) fn definition(&self) -> proc_macro2::TokenStream {
+        let struct_name = self.struct_name();
+        let vis = &self.vis;
+
+        let fields = self.inputs.iter().map(|input| {
+            let field = &input.field;
+            let ty = &input.ty;
+            if input.is_keyed() {
+                let key_ty = input.key_ty();
+                quote!(pub #field: Vec<(#key_ty, #ty)>)
+            } else {
+                quote!(pub #field: Option<#ty>)
+            }
+        });
+
+        quote! {
+            #[derive(Default, serde::Serialize, serde::Deserialize)]
+            #vis struct #struct_name {
+                #(#fields),*
+            }
+        }
+    }
+}
+
+impl ToTokens for InputsSnapshot {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let struct_name = self.struct_name();
+        let method_name = self.method_name();
+        let create_data_ident = &self.create_data_ident;
+
+        let method = if self.import {
+            let batch_update_method = &self.batch_update_method;
+            let fields = self.inputs.iter().map(|input| &input.field);
+
+            // what to write is worked out before the batch, which borrows the database:
+            // keys and inputs missing from the snapshot are only unset if they are set.
+            let mut prepares = vec![];
+            let mut imports = vec![];
+            for input in &self.inputs {
+                let field = &input.field;
+                let unset = format_ident!("unset_{}", field);
+                if input.is_keyed() {
+                    let keys = (0..input.keys.len())
+                        .map(|index| format_ident!("__key{}", index))
+                        .collect::<Vec<_>>();
+                    prepares.push(quote! {
+                        let #field = {
+                            let mut values = data
+                                .#field(self)
+                                .iter()
+                                .filter(|(_, slot)| slot.value(self).is_some())
+                                .map(|(key, _)| (key.clone(), None))
+                                .collect::<std::collections::HashMap<_, _>>();
+                            values.extend(#field.into_iter().map(|(key, value)| (key, Some(value))));
+                            values
+                        };
+                    });
+                    imports.push(quote! {
+                        for ((#(#keys,)*), value) in #field {
+                            match value {
+                                Some(value) => {
+                                    update.#field(#(#keys,)* value);
+                                }
+                                None => {
+                                    update.#unset(#(#keys),*);
+                                }
+                            }
+                        }
+                    });
+                } else {
+                    let read = input.read();
+                    prepares.push(quote! {
+                        let #field = match #field {
+                            Some(value) => Some(Some(value)),
+                            None if #read.is_some() => Some(None),
+                            None => None,
+                        };
+                    });
+                    imports.push(quote! {
+                        match #field {
+                            Some(Some(value)) => {
+                                update.#field(value);
+                            }
+                            Some(None) => {
+                                update.#unset();
+                            }
+                            None => {}
+                        }
+                    });
+                }
+            }
+
+            // only keyed inputs need to look at the map of what's currently stored.
+            let data = self
+                .inputs
+                .iter()
+                .any(InputStorage::is_keyed)
+                .then(|| quote!(let data = #create_data_ident(self);));
+
+            quote! {
+                fn #method_name(&mut self, snapshot: #struct_name)
+                where
+                    Self: Sized,
+                {
+                    let #struct_name { #(#fields),* } = snapshot;
+                    #data
+                    #(#prepares)*
+                    self.#batch_update_method(|update| {
+                        #(#imports)*
+                    });
+                }
+            }
+        } else {
+            let exports = self.inputs.iter().map(|input| {
+                let field = &input.field;
+                let clone = input.return_ref.then(|| quote!(.clone()));
+                if input.untracked {
+                    let read = input.read();
+                    quote!(#field: #read)
+                } else if input.is_keyed() {
+                    quote! {
+                        #field: data
+                            .#field(self)
+                            .iter()
+                            .filter_map(|(key, slot)| Some((key.clone(), slot.value(self)#clone?)))
+                            .collect()
+                    }
+                } else {
+                    quote!(#field: data.#field(self)#clone)
+                }
+            });
+
+            let data = self
+                .inputs
+                .iter()
+                .any(|input| !input.untracked)
+                .then(|| quote!(let data = #create_data_ident(self);));
+
+            quote! {
+                fn #method_name(&self) -> #struct_name {
+                    #data
+                    #struct_name {
+                        #(#exports),*
+                    }
+                }
+            }
+        };
+        method.to_tokens(tokens);
+    }
+}
+
+/// The `{Trait}InputObserver` trait enabled by `#[query_group(observer)]`.
+///
+/// The hook can't be a method of the query group trait itself: the blanket impl
+/// implements every method of it, leaving nothing to override. Instead, the setters
+/// require the database to implement this trait, whose hook defaults to a no-op.
+pub(crate) struct InputObserver {
+    pub(crate) trait_name: Ident,
+    pub(crate) vis: syn::Visibility,
+    pub(crate) inputs: Vec<InputStorage>,
+}
+
+impl InputObserver {
+    pub(crate) fn trait_name(trait_name: &Ident) -> Ident {
+        format_ident!("{}InputObserver", trait_name)
+    }
+
+    pub(crate) fn enum_name(trait_name: &Ident) -> Ident {
+        format_ident!("{}Input", trait_name)
+    }
+
+    fn hook_name(trait_name: &Ident) -> Ident {
+        format_ident!(
+            "on_{}_input_changed",
+            trait_name.to_string().to_snake_case()
+        )
+    }
+}
+
+impl ToTokens for InputObserver {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let vis = &self.vis;
+        let observer = Self::trait_name(&self.trait_name);
+        let input_enum = Self::enum_name(&self.trait_name);
+        let hook = Self::hook_name(&self.trait_name);
+        let variants = self.inputs.iter().map(InputStorage::variant);
+
+        quote! {
+            #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+            #vis enum #input_enum {
+                #(#variants),*
+            }
+
+            #vis trait #observer {
+                /// Called after one of the inputs was written.
+                fn #hook(&mut self, which: #input_enum, durability: salsa::Durability) {
+                    let _ = (which, durability);
+                }
+            }
+        }
+        .to_tokens(tokens);
+    }
+}
+
+pub(crate) struct Transparent {
+    pub(crate) signature: syn::Signature,
+    pub(crate) pat_and_tys: Vec<PatType>,
+    pub(crate) invoke: Option<Path>,
+}
+
+impl ToTokens for Transparent {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let sig = &self.signature;
+
+        let ty = self
+            .pat_and_tys
+            .iter()
+            .map(|pat_type| pat_type.pat.clone())
+            .collect::<Vec<Box<syn::Pat>>>();
+
+        let invoke = match &self.invoke {
+            Some(path) => path.to_token_stream(),
+            None => sig.ident.to_token_stream(),
+        };
+
+        let method = quote! {
+            #sig {
+                #invoke(self, #(#ty),*)
+            }
+        };
+
+        method.to_tokens(tokens);
+    }
+}
+pub(crate) struct Intern {
+    pub(crate) signature: syn::Signature,
+    pub(crate) pat_and_tys: Vec<PatType>,
+    pub(crate) interned_struct_path: Path,
+}
+
+impl ToTokens for Intern {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let sig = &self.signature;
+
+        let ty = self.pat_and_tys.to_vec();
+
+        let interned_pat = ty.first().unwrap();
+        let interned_pat = &interned_pat.pat;
+
+        let wrapper_struct = self.interned_struct_path.to_token_stream();
+
+        let method = quote! {
+            #sig {
+                #wrapper_struct::new(self, #interned_pat)
+            }
+        };
+
+        method.to_tokens(tokens);
+    }
+}
+
+pub(crate) struct Lookup {
     pub(crate) signature: syn::Signature,
     pub(crate) pat_and_tys: Vec<PatType>,
     pub(crate) return_ty: Type,
@@ -284,15 +2389,11 @@ impl Lookup {
 
         let ident = format_ident!("lookup_{}", sig.ident);
 
-        let ty = self
-            .pat_and_tys
-            .iter()
-            .map(|pat_type| pat_type.clone())
-            .collect::<Vec<syn::PatType>>();
+        let ty = self.pat_and_tys.to_vec();
 
         let interned_key = &self.return_ty;
 
-        let interned_pat = ty.iter().next().unwrap();
+        let interned_pat = ty.first().unwrap();
         let interned_return_ty = &interned_pat.ty;
 
         self.signature = parse_quote!(
@@ -316,11 +2417,14 @@ impl ToTokens for Lookup {
     }
 }
 
+// like `AccessorKind`, only ever turned into tokens.
+#[allow(clippy::large_enum_variant)]
 pub(crate) enum Queries {
     TrackedQuery(TrackedQuery),
     InputQuery(InputQuery),
     Intern(Intern),
     Transparent(Transparent),
+    Accumulated(Accumulated),
 }
 
 impl ToTokens for Queries {
@@ -330,6 +2434,7 @@ impl ToTokens for Queries {
             Queries::InputQuery(input_query) => input_query.to_tokens(tokens),
             Queries::Transparent(transparent) => transparent.to_tokens(tokens),
             Queries::Intern(intern) => intern.to_tokens(tokens),
+            Queries::Accumulated(accumulated) => accumulated.to_tokens(tokens),
         }
     }
 }
@@ -0,0 +1,100 @@
+use query_group::query_group;
+use salsa::Accumulator;
+
+#[salsa::accumulator]
+#[derive(Debug, PartialEq, Eq)]
+pub struct Diagnostic(String);
+
+#[query_group]
+pub trait DiagnosticsDatabase: salsa::Database {
+    #[salsa::input]
+    fn input_string(&self) -> String;
+
+    fn parse(&self) -> Vec<String>;
+
+    fn word_len(&self, index: usize) -> usize;
+
+    #[salsa::accumulated(query = parse, accumulator = Diagnostic)]
+    fn parse_diagnostics(&self) -> Vec<Diagnostic>;
+
+    #[salsa::accumulated(query = word_len, accumulator = Diagnostic)]
+    fn word_len_diagnostics(&self, index: usize) -> Vec<Diagnostic>;
+}
+
+// a second query group accumulating from a query with the same name.
+#[query_group]
+pub trait LintsDatabase: salsa::Database {
+    #[salsa::input]
+    fn lint_source(&self) -> String;
+
+    #[salsa::invoke(parse_lints)]
+    fn parse(&self) -> Vec<String>;
+
+    #[salsa::accumulated(query = parse, accumulator = Diagnostic)]
+    fn lint_diagnostics(&self) -> Vec<Diagnostic>;
+}
+
+fn parse_lints(db: &dyn LintsDatabase) -> Vec<String> {
+    let lints = db
+        .lint_source()
+        .lines()
+        .map(String::from)
+        .collect::<Vec<_>>();
+    if lints.is_empty() {
+        Diagnostic(String::from("no lints")).accumulate(db);
+    }
+    lints
+}
+
+fn parse(db: &dyn DiagnosticsDatabase) -> Vec<String> {
+    let words = db
+        .input_string()
+        .split(' ')
+        .map(String::from)
+        .collect::<Vec<_>>();
+    for index in 0..words.len() {
+        db.word_len(index);
+    }
+    words
+}
+
+fn word_len(db: &dyn DiagnosticsDatabase, index: usize) -> usize {
+    let len = db.input_string().split(' ').nth(index).map_or(0, str::len);
+    if len == 0 {
+        Diagnostic(format!("word {index} is empty")).accumulate(db);
+    }
+    len
+}
+
+#[test]
+fn collect_diagnostics() {
+    let mut db = salsa::DatabaseImpl::new();
+
+    db.set_input_string(String::from("fn  main"));
+    assert_eq!(DiagnosticsDatabase::parse(&db), ["fn", "", "main"]);
+    assert_eq!(
+        db.parse_diagnostics(),
+        [Diagnostic(String::from("word 1 is empty"))]
+    );
+    assert!(db.word_len_diagnostics(0).is_empty());
+
+    db.set_input_string(String::from("fn main"));
+    assert!(db.parse_diagnostics().is_empty());
+}
+
+#[test]
+fn query_groups_with_the_same_target_name() {
+    let mut db = salsa::DatabaseImpl::new();
+
+    db.set_input_string(String::from("fn  main"));
+    db.set_lint_source(String::new());
+    assert_eq!(
+        db.parse_diagnostics(),
+        [Diagnostic(String::from("word 1 is empty"))]
+    );
+    assert_eq!(
+        db.lint_diagnostics(),
+        [Diagnostic(String::from("no lints"))]
+    );
+    assert!(LintsDatabase::parse(&db).is_empty());
+}
@@ -0,0 +1,100 @@
+use expect_test::expect;
+
+mod logger_db;
+use logger_db::LoggerDb;
+use query_group::query_group;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FileId(u32);
+
+#[query_group]
+pub trait WorkspaceDatabase: salsa::Database {
+    #[salsa::input]
+    fn input_string(&self) -> String;
+
+    #[salsa::input]
+    fn crate_names(&self) -> Vec<String>;
+
+    #[salsa::input]
+    fn file_text(&self, file: FileId) -> String;
+
+    fn total_len(&self) -> usize;
+}
+
+fn total_len(db: &dyn WorkspaceDatabase) -> usize {
+    db.input_string().len() + db.crate_names().len() + db.file_text(FileId(0)).len()
+}
+
+#[test]
+fn batch_update() {
+    let mut db = salsa::DatabaseImpl::new();
+
+    db.update_workspace_database_inputs(|b| {
+        b.input_string(String::from("Hello, world!"));
+        b.crate_names_with_durability(vec![String::from("std")], salsa::Durability::HIGH);
+        b.file_text(FileId(0), String::from("fn main() {}"))
+            .file_text(FileId(1), String::from("struct S;"));
+    });
+
+    assert_eq!(db.input_string(), "Hello, world!");
+    assert_eq!(db.crate_names(), vec![String::from("std")]);
+    assert_eq!(db.file_text(FileId(1)), "struct S;");
+    assert_eq!(db.total_len(), 26);
+
+    db.update_workspace_database_inputs(|b| {
+        b.input_string(String::from("Hello"));
+    });
+    assert_eq!(db.crate_names(), vec![String::from("std")]);
+    assert_eq!(db.total_len(), 18);
+}
+
+#[test]
+fn batch_update_is_a_single_revision() {
+    let mut db = LoggerDb::default();
+
+    db.update_workspace_database_inputs(|b| {
+        b.input_string(String::from("Hello"));
+        b.crate_names(vec![]);
+        b.file_text(FileId(0), String::from("fn main() {}"));
+    });
+    assert_eq!(db.total_len(), 17);
+    db.assert_logs(expect![[r#"
+        [
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillExecute { database_key: create_data_WorkspaceDatabase(Id(0)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(DidValidateMemoizedValue { database_key: create_data_WorkspaceDatabase(Id(0)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillExecute { database_key: total_len_shim(Id(400)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillExecute { database_key: slot_file_text_WorkspaceDatabase(Id(c00)) })",
+        ]"#]]);
+
+    db.update_workspace_database_inputs(|b| {
+        b.input_string(String::from("Hello, world!"));
+        b.crate_names(vec![String::from("std")]);
+        b.file_text(FileId(0), String::new());
+    });
+    assert_eq!(db.total_len(), 14);
+    db.assert_logs(expect![[r#"
+        [
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(DidValidateMemoizedValue { database_key: create_data_WorkspaceDatabase(Id(0)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillExecute { database_key: total_len_shim(Id(400)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(DidValidateMemoizedValue { database_key: slot_file_text_WorkspaceDatabase(Id(c00)) })",
+        ]"#]]);
+
+    // all three inputs changed in the same revision.
+    let changed_at = db.input_string_changed_at();
+    assert_eq!(db.crate_names_changed_at(), changed_at);
+    assert_eq!(db.file_text_changed_at(FileId(0)), changed_at);
+}
@@ -94,12 +94,7 @@ fn cycle_a(db: &dyn CycleDatabase, abc: ABC) -> Result<(), Error> {
     abc.a(db).invoke(db, abc)
 }
 
-fn recover_a(
-    _db: &dyn CycleDatabase,
-    cycle: &salsa::Cycle,
-    _: CycleDatabaseData,
-    _abc: ABC,
-) -> Result<(), Error> {
+fn recover_a(_db: &dyn CycleDatabase, cycle: &salsa::Cycle, _abc: ABC) -> Result<(), Error> {
     Err(Error {
         cycle: cycle.participant_keys().map(|k| format!("{k:?}")).collect(),
     })
@@ -109,12 +104,7 @@ fn cycle_b(db: &dyn CycleDatabase, abc: ABC) -> Result<(), Error> {
     abc.b(db).invoke(db, abc)
 }
 
-fn recover_b(
-    _db: &dyn CycleDatabase,
-    cycle: &salsa::Cycle,
-    _: CycleDatabaseData,
-    _abc: ABC,
-) -> Result<(), Error> {
+fn recover_b(_db: &dyn CycleDatabase, cycle: &salsa::Cycle, _abc: ABC) -> Result<(), Error> {
     Err(Error {
         cycle: cycle.participant_keys().map(|k| format!("{k:?}")).collect(),
     })
@@ -0,0 +1,80 @@
+use query_group::query_group;
+
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+struct Error {
+    cycle: Vec<String>,
+}
+
+impl Error {
+    fn new(cycle: Vec<String>) -> Self {
+        Error { cycle }
+    }
+}
+
+#[salsa::input]
+struct Loop {
+    cyclic: bool,
+}
+
+#[query_group]
+trait FallbackDatabase: salsa::Database {
+    #[salsa::cycle(fallback = 0)]
+    fn depth_a(&self, input: Loop) -> u32;
+
+    #[salsa::cycle(fallback = 0)]
+    fn depth_b(&self, input: Loop) -> u32;
+
+    #[salsa::cycle(error = Error::new)]
+    fn check_a(&self, input: Loop) -> Result<(), Error>;
+
+    #[salsa::cycle(error = Error::new)]
+    fn check_b(&self, input: Loop) -> Result<(), Error>;
+}
+
+fn depth_a(db: &dyn FallbackDatabase, input: Loop) -> u32 {
+    db.depth_b(input) + 1
+}
+
+fn depth_b(db: &dyn FallbackDatabase, input: Loop) -> u32 {
+    if input.cyclic(db) {
+        db.depth_a(input) + 1
+    } else {
+        0
+    }
+}
+
+fn check_a(db: &dyn FallbackDatabase, input: Loop) -> Result<(), Error> {
+    db.check_b(input)
+}
+
+fn check_b(db: &dyn FallbackDatabase, input: Loop) -> Result<(), Error> {
+    if input.cyclic(db) {
+        db.check_a(input)
+    } else {
+        Ok(())
+    }
+}
+
+#[test]
+fn fallback_value() {
+    let db = salsa::DatabaseImpl::new();
+
+    let input = Loop::new(&db, false);
+    assert_eq!(db.depth_a(input), 1);
+
+    let input = Loop::new(&db, true);
+    assert_eq!(db.depth_a(input), 0);
+    assert_eq!(db.depth_b(input), 0);
+}
+
+#[test]
+fn error_with_participants() {
+    let db = salsa::DatabaseImpl::new();
+
+    let input = Loop::new(&db, false);
+    assert!(db.check_a(input).is_ok());
+
+    let input = Loop::new(&db, true);
+    let err = db.check_a(input).unwrap_err();
+    assert_eq!(err.cycle, ["check_a", "check_b"]);
+}
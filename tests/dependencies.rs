@@ -0,0 +1,91 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use expect_test::expect;
+
+mod logger_db;
+use logger_db::LoggerDb;
+use query_group::query_group;
+
+#[query_group]
+pub trait DependenciesDatabase: salsa::Database {
+    #[salsa::input]
+    fn input_string(&self) -> String;
+
+    #[salsa::dependencies]
+    fn length(&self, key: ()) -> usize;
+
+    fn doubled_length(&self) -> usize;
+
+    #[salsa::dependencies]
+    fn prefix_length(&self, max: usize) -> usize;
+}
+
+fn length(db: &dyn DependenciesDatabase, key: ()) -> usize {
+    let _ = key;
+    db.input_string().len()
+}
+
+fn doubled_length(db: &dyn DependenciesDatabase) -> usize {
+    db.length(()) * 2
+}
+
+static PREFIX_LENGTH_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+fn prefix_length(db: &dyn DependenciesDatabase, max: usize) -> usize {
+    PREFIX_LENGTH_CALLS.fetch_add(1, Ordering::SeqCst);
+    db.input_string().len().min(max)
+}
+
+#[test]
+fn dependencies_query() {
+    let mut db = LoggerDb::default();
+
+    db.set_input_string(String::from("Hello, world!"));
+    assert_eq!(db.doubled_length(), 26);
+    db.assert_logs(expect![[r#"
+        [
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillExecute { database_key: create_data_DependenciesDatabase(Id(0)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(DidValidateMemoizedValue { database_key: create_data_DependenciesDatabase(Id(0)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillExecute { database_key: doubled_length_shim(Id(400)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillExecute { database_key: length_shim(Id(800)) })",
+            "salsa_event(WillCheckCancellation)",
+        ]"#]]);
+
+    db.set_input_string(String::from("Hello"));
+    assert_eq!(db.doubled_length(), 10);
+    db.assert_logs(expect![[r#"
+        [
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(DidValidateMemoizedValue { database_key: create_data_DependenciesDatabase(Id(0)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillExecute { database_key: length_shim(Id(800)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillExecute { database_key: doubled_length_shim(Id(400)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillCheckCancellation)",
+        ]"#]]);
+}
+
+#[test]
+fn dependencies_query_keeps_one_value() {
+    let mut db = LoggerDb::default();
+
+    db.set_input_string(String::from("Hello, world!"));
+    assert_eq!(db.prefix_length(5), 5);
+    assert_eq!(db.prefix_length(8), 8);
+    assert_eq!(PREFIX_LENGTH_CALLS.load(Ordering::SeqCst), 2);
+
+    // starting a new revision evicts every value but the most recently used one, while
+    // the dependencies of both are kept.
+    salsa::Database::synthetic_write(&mut db, salsa::Durability::LOW);
+    assert_eq!(db.prefix_length(8), 8);
+    assert_eq!(PREFIX_LENGTH_CALLS.load(Ordering::SeqCst), 2);
+    assert_eq!(db.prefix_length(5), 5);
+    assert_eq!(PREFIX_LENGTH_CALLS.load(Ordering::SeqCst), 3);
+}
@@ -0,0 +1,45 @@
+use query_group::query_group;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FileId(u32);
+
+#[query_group]
+pub trait ConfigDatabase: salsa::Database {
+    #[salsa::input(default = 4)]
+    fn tab_width(&self) -> usize;
+
+    #[salsa::input(default)]
+    fn cfg_flags(&self) -> Vec<String>;
+
+    #[salsa::input(default = String::from("<missing>"))]
+    fn file_text(&self, file: FileId) -> String;
+
+    fn indent(&self, depth: usize) -> usize;
+}
+
+fn indent(db: &dyn ConfigDatabase, depth: usize) -> usize {
+    db.tab_width() * depth
+}
+
+#[test]
+fn default_values() {
+    let db = salsa::DatabaseImpl::new();
+
+    assert_eq!(db.tab_width(), 4);
+    assert_eq!(db.cfg_flags(), Vec::<String>::new());
+    assert_eq!(db.file_text(FileId(0)), "<missing>");
+    assert_eq!(db.indent(2), 8);
+}
+
+#[test]
+fn set_overrides_default() {
+    let mut db = salsa::DatabaseImpl::new();
+
+    db.set_tab_width(2);
+    db.set_file_text(FileId(0), String::from("fn main() {}"));
+
+    assert_eq!(db.tab_width(), 2);
+    assert_eq!(db.file_text(FileId(0)), "fn main() {}");
+    assert_eq!(db.file_text(FileId(1)), "<missing>");
+    assert_eq!(db.indent(2), 4);
+}
@@ -0,0 +1,34 @@
+use query_group::query_group;
+
+const CFG_DURABILITY: salsa::Durability = salsa::Durability::MEDIUM;
+
+#[query_group]
+pub trait DurabilityDatabase: salsa::Database {
+    #[salsa::input(durability = HIGH)]
+    fn crate_graph(&self) -> Vec<String>;
+
+    #[salsa::input(durability = CFG_DURABILITY)]
+    fn cfg_options(&self) -> Vec<String>;
+
+    fn crate_count(&self) -> usize;
+}
+
+fn crate_count(db: &dyn DurabilityDatabase) -> usize {
+    db.crate_graph().len()
+}
+
+#[test]
+fn declared_durability() {
+    let mut db = salsa::DatabaseImpl::new();
+
+    db.set_crate_graph(vec![String::from("std"), String::from("core")]);
+    db.set_cfg_options(vec![String::from("test")]);
+    assert_eq!(db.crate_count(), 2);
+    assert_eq!(db.crate_graph_durability(), salsa::Durability::HIGH);
+    assert_eq!(db.cfg_options_durability(), salsa::Durability::MEDIUM);
+
+    // the explicit variant still overrides the declared durability.
+    db.set_crate_graph_with_durability(vec![String::from("std")], salsa::Durability::LOW);
+    assert_eq!(db.crate_count(), 1);
+    assert_eq!(db.crate_graph_durability(), salsa::Durability::LOW);
+}
@@ -0,0 +1,73 @@
+use query_group::query_group;
+use salsa::Durability;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FileId(u32);
+
+#[query_group]
+pub trait SourceDatabase: salsa::Database {
+    #[salsa::input(durability = HIGH)]
+    fn crate_graph(&self) -> Vec<FileId>;
+
+    #[salsa::input]
+    fn file_text(&self, file: FileId) -> String;
+
+    fn crate_graph_is_newer(&self) -> bool;
+}
+
+fn crate_graph_is_newer(db: &dyn SourceDatabase) -> bool {
+    db.crate_graph_changed_at() > db.file_text_changed_at(FileId(0))
+}
+
+#[test]
+fn durability_of_inputs() {
+    let mut db = salsa::DatabaseImpl::new();
+    assert_eq!(db.crate_graph_durability(), Durability::LOW);
+
+    db.set_crate_graph(vec![FileId(0)]);
+    db.set_file_text(FileId(0), String::new());
+    db.set_file_text_with_durability(FileId(1), String::new(), Durability::MEDIUM);
+    assert_eq!(db.crate_graph_durability(), Durability::HIGH);
+    assert_eq!(db.file_text_durability(FileId(0)), Durability::LOW);
+    assert_eq!(db.file_text_durability(FileId(1)), Durability::MEDIUM);
+    assert_eq!(db.file_text_durability(FileId(2)), Durability::LOW);
+
+    // setters without a durability keep the previous one.
+    db.update_source_database_inputs(|update| {
+        update.file_text(FileId(1), String::from("fn main() {}"));
+    });
+    assert_eq!(db.file_text_durability(FileId(1)), Durability::MEDIUM);
+}
+
+#[test]
+fn changed_at_of_inputs() {
+    let mut db = salsa::DatabaseImpl::new();
+    // inputs that were never written report the revision they were created in.
+    let created_at = db.file_text_changed_at(FileId(0));
+    assert_eq!(db.crate_graph_changed_at(), created_at);
+
+    db.set_crate_graph(vec![FileId(0)]);
+    db.set_file_text(FileId(0), String::new());
+    let crate_graph_changed_at = db.crate_graph_changed_at();
+    let file_text_changed_at = db.file_text_changed_at(FileId(0));
+    assert!(created_at < crate_graph_changed_at);
+    assert!(crate_graph_changed_at < file_text_changed_at);
+    assert_eq!(db.file_text_changed_at(FileId(1)), created_at);
+
+    db.set_file_text(FileId(0), String::from("fn main() {}"));
+    assert_eq!(db.crate_graph_changed_at(), crate_graph_changed_at);
+    assert!(db.file_text_changed_at(FileId(0)) > file_text_changed_at);
+}
+
+#[test]
+fn metadata_read_in_queries() {
+    let mut db = salsa::DatabaseImpl::new();
+
+    db.set_file_text(FileId(0), String::new());
+    db.set_crate_graph(vec![FileId(0)]);
+    assert!(db.crate_graph_is_newer());
+
+    // the query depends on the metadata, so it sees the new revision.
+    db.set_file_text(FileId(0), String::from("fn main() {}"));
+    assert!(!db.crate_graph_is_newer());
+}
@@ -0,0 +1,63 @@
+use expect_test::expect;
+
+mod logger_db;
+use logger_db::LoggerDb;
+use query_group::query_group;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FileId(u32);
+
+#[query_group(observer)]
+pub trait ConfigDatabase: salsa::Database {
+    #[salsa::input(durability = HIGH)]
+    fn watched_paths(&self) -> Vec<String>;
+
+    #[salsa::input]
+    fn file_text(&self, file: FileId) -> String;
+}
+
+impl ConfigDatabaseInputObserver for LoggerDb {
+    fn on_config_database_input_changed(
+        &mut self,
+        which: ConfigDatabaseInput,
+        durability: salsa::Durability,
+    ) {
+        let durability = if durability == salsa::Durability::HIGH {
+            "HIGH"
+        } else if durability == salsa::Durability::MEDIUM {
+            "MEDIUM"
+        } else {
+            "LOW"
+        };
+        self.push_log(format!("input_changed({which:?}, {durability})"));
+    }
+}
+
+#[test]
+fn setters_notify_observer() {
+    let mut db = LoggerDb::default();
+
+    db.set_watched_paths(vec![String::from("src")]);
+    db.set_file_text_with_durability(FileId(0), String::new(), salsa::Durability::MEDIUM);
+    db.assert_logs(expect![[r#"
+        [
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillExecute { database_key: create_data_ConfigDatabase(Id(0)) })",
+            "input_changed(WatchedPaths, HIGH)",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(DidValidateMemoizedValue { database_key: create_data_ConfigDatabase(Id(0)) })",
+            "input_changed(FileText, MEDIUM)",
+        ]"#]]);
+
+    db.update_watched_paths(|paths| paths.push(String::from("tests")));
+    db.unset_file_text(FileId(0));
+    db.assert_logs(expect![[r#"
+        [
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(DidValidateMemoizedValue { database_key: create_data_ConfigDatabase(Id(0)) })",
+            "input_changed(WatchedPaths, HIGH)",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(DidValidateMemoizedValue { database_key: create_data_ConfigDatabase(Id(0)) })",
+            "input_changed(FileText, MEDIUM)",
+        ]"#]]);
+}
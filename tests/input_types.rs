@@ -0,0 +1,47 @@
+use query_group::query_group;
+
+#[query_group]
+pub trait InputTypesDatabase: salsa::Database {
+    #[salsa::input]
+    fn pair(&self) -> (u32, u32);
+
+    #[salsa::input]
+    fn bytes(&self) -> [u8; 4];
+
+    #[salsa::input]
+    fn name(&self) -> &'static str;
+
+    #[salsa::input]
+    fn names(&self) -> &'static [&'static str];
+
+    #[salsa::input]
+    fn callback(&self) -> fn(u32) -> u32;
+
+    fn sum(&self) -> u32;
+}
+
+fn sum(db: &dyn InputTypesDatabase) -> u32 {
+    let (a, b) = db.pair();
+    db.callback()(a + b)
+}
+
+fn double(value: u32) -> u32 {
+    value * 2
+}
+
+#[test]
+fn non_path_input_types() {
+    let mut db = salsa::DatabaseImpl::new();
+
+    db.set_pair((1, 2));
+    db.set_bytes([1, 2, 3, 4]);
+    db.set_name("hello");
+    db.set_names(&["a", "b"]);
+    db.set_callback(double);
+
+    assert_eq!(db.pair(), (1, 2));
+    assert_eq!(db.bytes(), [1, 2, 3, 4]);
+    assert_eq!(db.name(), "hello");
+    assert_eq!(db.names(), &["a", "b"]);
+    assert_eq!(db.sum(), 6);
+}
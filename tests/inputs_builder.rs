@@ -0,0 +1,54 @@
+use query_group::query_group;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FileId(u32);
+
+#[query_group]
+pub trait SourceDatabase: salsa::Database {
+    #[salsa::input]
+    fn crate_name(&self) -> String;
+
+    #[salsa::input]
+    fn crate_root(&self) -> FileId;
+
+    #[salsa::input(default = 4)]
+    fn tab_width(&self) -> u32;
+
+    #[salsa::input]
+    fn file_text(&self, file: FileId) -> String;
+
+    fn root_len(&self) -> usize;
+}
+
+fn root_len(db: &dyn SourceDatabase) -> usize {
+    db.file_text(db.crate_root()).len()
+}
+
+#[test]
+fn build_all_inputs() {
+    let mut db = salsa::DatabaseImpl::new();
+
+    SourceDatabaseInputsBuilder::new()
+        .file_text(FileId(0), String::from("fn main() {}"))
+        .crate_root(FileId(0))
+        .crate_name(String::from("hello"))
+        .build_into(&mut db);
+
+    assert_eq!(db.crate_name(), "hello");
+    assert_eq!(db.tab_width(), 4);
+    assert_eq!(db.root_len(), 12);
+}
+
+#[test]
+fn build_with_optional_inputs() {
+    let mut db = salsa::DatabaseImpl::new();
+
+    SourceDatabaseInputsBuilder::default()
+        .crate_name(String::from("hello"))
+        .tab_width(2)
+        .crate_root(FileId(0))
+        .build_into(&mut db);
+
+    assert_eq!(db.tab_width(), 2);
+    assert!(!db.has_file_text(FileId(0)));
+}
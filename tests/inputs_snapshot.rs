@@ -0,0 +1,57 @@
+#![cfg(feature = "serde")]
+
+use query_group::query_group;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct FileId(u32);
+
+#[query_group(snapshot)]
+pub trait SourceDatabase: salsa::Database {
+    #[salsa::input]
+    fn file_text(&self, file: FileId) -> String;
+
+    #[salsa::input(default = 4)]
+    fn tab_width(&self) -> u32;
+
+    #[salsa::input]
+    fn crate_name(&self) -> String;
+
+    fn file_len(&self, file: FileId) -> usize;
+}
+
+fn file_len(db: &dyn SourceDatabase, file: FileId) -> usize {
+    db.file_text(file).len()
+}
+
+fn assert_serde<T: serde::Serialize + serde::de::DeserializeOwned>(_: &T) {}
+
+#[test]
+fn export_and_import() {
+    let mut db = salsa::DatabaseImpl::new();
+    db.set_file_text(FileId(0), String::from("fn main() {}"));
+    db.set_file_text(FileId(1), String::from("struct S;"));
+    db.set_tab_width(2);
+
+    let snapshot = db.export_source_database_inputs();
+    assert_serde(&snapshot);
+    assert_eq!(snapshot.file_text.len(), 2);
+    assert_eq!(snapshot.tab_width, Some(2));
+    assert_eq!(snapshot.crate_name, None);
+
+    let mut fresh = salsa::DatabaseImpl::new();
+    fresh.set_file_text(FileId(2), String::from("mod m;"));
+    fresh.set_crate_name(String::from("core"));
+    fresh.import_source_database_inputs(snapshot);
+
+    assert_eq!(fresh.file_len(FileId(0)), 12);
+    assert_eq!(fresh.file_len(FileId(1)), 9);
+    assert!(!fresh.has_file_text(FileId(2)));
+    assert_eq!(fresh.tab_width(), 2);
+    assert!(!fresh.has_crate_name());
+
+    // the whole snapshot is imported in a single revision.
+    let changed_at = fresh.tab_width_changed_at();
+    assert_eq!(fresh.file_text_changed_at(FileId(0)), changed_at);
+    assert_eq!(fresh.file_text_changed_at(FileId(2)), changed_at);
+    assert_eq!(fresh.crate_name_changed_at(), changed_at);
+}
@@ -0,0 +1,108 @@
+use query_group::query_group;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FileId(u32);
+
+#[query_group(journal)]
+pub trait ConfigDatabase: salsa::Database {
+    #[salsa::input(default)]
+    fn tab_width(&self) -> u32;
+
+    #[salsa::input]
+    fn file_text(&self, file: FileId) -> String;
+
+    fn indent(&self) -> String;
+}
+
+#[query_group(journal = 2)]
+pub trait HistoryDatabase: salsa::Database {
+    #[salsa::input]
+    fn revision_name(&self) -> String;
+}
+
+fn indent(db: &dyn ConfigDatabase) -> String {
+    " ".repeat(db.tab_width() as usize)
+}
+
+#[test]
+fn undo_and_redo() {
+    let mut db = salsa::DatabaseImpl::new();
+
+    db.set_tab_width(4);
+    db.set_tab_width_with_durability(8, salsa::Durability::HIGH);
+    assert_eq!(db.indent().len(), 8);
+
+    assert!(db.undo_config_database_inputs());
+    assert_eq!(db.indent().len(), 4);
+    assert!(db.undo_config_database_inputs());
+    assert_eq!(db.tab_width(), 0);
+    assert!(!db.undo_config_database_inputs());
+
+    assert!(db.redo_config_database_inputs());
+    assert_eq!(db.tab_width(), 4);
+    assert!(db.redo_config_database_inputs());
+    assert_eq!(db.indent().len(), 8);
+    assert!(!db.redo_config_database_inputs());
+}
+
+#[test]
+fn undo_keyed_input() {
+    let mut db = salsa::DatabaseImpl::new();
+
+    db.set_file_text(FileId(0), String::from("fn main() {"));
+    db.update_file_text(FileId(0), |text| text.push('}'));
+
+    assert!(db.undo_config_database_inputs());
+    assert_eq!(db.file_text(FileId(0)), "fn main() {");
+    assert!(db.undo_config_database_inputs());
+    assert!(!db.has_file_text(FileId(0)));
+
+    assert!(db.redo_config_database_inputs());
+    assert_eq!(db.file_text(FileId(0)), "fn main() {");
+}
+
+#[test]
+fn set_after_undo_clears_redo() {
+    let mut db = salsa::DatabaseImpl::new();
+
+    db.set_tab_width(4);
+    assert!(db.undo_config_database_inputs());
+
+    db.set_tab_width(2);
+    assert!(!db.redo_config_database_inputs());
+    assert_eq!(db.tab_width(), 2);
+}
+
+#[test]
+fn clear_journal() {
+    let mut db = salsa::DatabaseImpl::new();
+
+    db.set_tab_width(4);
+    db.set_tab_width(8);
+    assert!(db.undo_config_database_inputs());
+
+    db.clear_config_database_journal();
+    assert!(!db.undo_config_database_inputs());
+    assert!(!db.redo_config_database_inputs());
+    assert_eq!(db.tab_width(), 4);
+}
+
+#[test]
+fn journal_capacity() {
+    let mut db = salsa::DatabaseImpl::new();
+
+    for name in ["a", "b", "c"] {
+        db.set_revision_name(String::from(name));
+    }
+
+    // only the last two writes are kept, so the first one can't be undone.
+    assert!(db.undo_history_database_inputs());
+    assert_eq!(db.revision_name(), "b");
+    assert!(db.undo_history_database_inputs());
+    assert_eq!(db.revision_name(), "a");
+    assert!(!db.undo_history_database_inputs());
+
+    assert!(db.redo_history_database_inputs());
+    assert!(db.redo_history_database_inputs());
+    assert_eq!(db.revision_name(), "c");
+}
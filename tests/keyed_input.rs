@@ -0,0 +1,85 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use query_group::query_group;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FileId(u32);
+
+#[query_group]
+pub trait SourceDatabase: salsa::Database {
+    #[salsa::input]
+    fn file_text(&self, file: FileId) -> Arc<str>;
+
+    #[salsa::input]
+    fn file_line(&self, file: FileId, line: u32) -> String;
+
+    fn file_len(&self, file: FileId) -> usize;
+
+    fn counted_file_len(&self, file: FileId) -> usize;
+}
+
+fn file_len(db: &dyn SourceDatabase, file: FileId) -> usize {
+    db.file_text(file).len()
+}
+
+static COUNTED_FILE_LEN_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+fn counted_file_len(db: &dyn SourceDatabase, file: FileId) -> usize {
+    COUNTED_FILE_LEN_CALLS.fetch_add(1, Ordering::SeqCst);
+    db.file_text(file).len()
+}
+
+#[test]
+fn keyed_input_round_trip() {
+    let mut db = salsa::DatabaseImpl::new();
+
+    db.set_file_text(FileId(0), Arc::from("fn main() {}"));
+    db.set_file_text(FileId(1), Arc::from("struct S;"));
+
+    assert_eq!(&*db.file_text(FileId(0)), "fn main() {}");
+    assert_eq!(&*db.file_text(FileId(1)), "struct S;");
+}
+
+#[test]
+fn keyed_input_multiple_keys() {
+    let mut db = salsa::DatabaseImpl::new();
+
+    db.set_file_line(FileId(0), 1, String::from("fn main() {"));
+    db.set_file_line(FileId(0), 2, String::from("}"));
+
+    assert_eq!(db.file_line(FileId(0), 1), "fn main() {");
+    assert_eq!(db.file_line(FileId(0), 2), "}");
+}
+
+#[test]
+fn keyed_input_with_query() {
+    let mut db = salsa::DatabaseImpl::new();
+
+    db.set_file_text(FileId(0), Arc::from("fn main() {}"));
+    db.set_file_text_with_durability(FileId(1), Arc::from("struct S;"), salsa::Durability::HIGH);
+    assert_eq!(db.file_len(FileId(0)), 12);
+    assert_eq!(db.file_len(FileId(1)), 9);
+
+    db.set_file_text(FileId(1), Arc::from("struct Struct;"));
+    assert_eq!(db.file_len(FileId(0)), 12);
+    assert_eq!(db.file_len(FileId(1)), 14);
+}
+
+#[test]
+fn keyed_input_new_key_does_not_invalidate_other_keys() {
+    let mut db = salsa::DatabaseImpl::new();
+
+    db.set_file_text(FileId(0), Arc::from("fn main() {}"));
+    assert_eq!(db.counted_file_len(FileId(0)), 12);
+    assert_eq!(COUNTED_FILE_LEN_CALLS.load(Ordering::SeqCst), 1);
+
+    // Adding a key changes the map but not the slot of `FileId(0)`.
+    db.set_file_text(FileId(1), Arc::from("struct S;"));
+    assert_eq!(db.counted_file_len(FileId(0)), 12);
+    assert_eq!(COUNTED_FILE_LEN_CALLS.load(Ordering::SeqCst), 1);
+
+    db.set_file_text(FileId(0), Arc::from("fn main() { }"));
+    assert_eq!(db.counted_file_len(FileId(0)), 13);
+    assert_eq!(COUNTED_FILE_LEN_CALLS.load(Ordering::SeqCst), 2);
+}
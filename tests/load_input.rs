@@ -0,0 +1,77 @@
+use expect_test::expect;
+
+mod logger_db;
+use logger_db::LoggerDb;
+use query_group::query_group;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FileId(u32);
+
+#[query_group]
+pub trait VfsDatabase: salsa::Database {
+    #[salsa::input(load = load_file_text)]
+    fn file_text(&self, file: FileId) -> String;
+
+    #[salsa::input(load = load_root, return_ref)]
+    fn root(&self) -> String;
+}
+
+fn load_file_text(_db: &dyn VfsDatabase, file: FileId) -> String {
+    format!("// file {}", file.0)
+}
+
+fn load_root(_db: &dyn VfsDatabase) -> String {
+    String::from("/")
+}
+
+#[test]
+fn load_on_first_read() {
+    let db = LoggerDb::default();
+
+    assert_eq!(db.file_text(FileId(0)), "// file 0");
+    assert_eq!(db.file_text(FileId(0)), "// file 0");
+    assert_eq!(db.file_text(FileId(1)), "// file 1");
+    db.assert_logs(expect![[r#"
+        [
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillExecute { database_key: create_data_VfsDatabase(Id(0)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillExecute { database_key: slot_file_text_VfsDatabase(Id(800)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillExecute { database_key: load_file_text_VfsDatabase(Id(c00)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillExecute { database_key: slot_file_text_VfsDatabase(Id(801)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillExecute { database_key: load_file_text_VfsDatabase(Id(c01)) })",
+        ]"#]]);
+    assert!(!db.has_file_text(FileId(0)));
+
+    assert_eq!(db.root(), "/");
+}
+
+#[test]
+fn set_overrides_loader() {
+    let mut db = LoggerDb::default();
+
+    db.set_file_text(FileId(0), String::from("fn main() {}"));
+    assert_eq!(db.file_text(FileId(0)), "fn main() {}");
+    db.assert_logs(expect![[r#"
+        [
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillExecute { database_key: create_data_VfsDatabase(Id(0)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(DidValidateMemoizedValue { database_key: create_data_VfsDatabase(Id(0)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillExecute { database_key: slot_file_text_VfsDatabase(Id(c00)) })",
+        ]"#]]);
+
+    db.update_root(|root| root.push_str("src"));
+    assert_eq!(db.root(), "/src");
+}
@@ -0,0 +1,47 @@
+use query_group::query_group;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FileId(u32);
+
+#[query_group]
+pub trait SourceDatabase: salsa::Database {
+    #[salsa::input(return_ref)]
+    fn file_text(&self, file: FileId) -> String;
+
+    #[salsa::input(return_ref, skip_unchanged)]
+    fn crate_graph(&self) -> Vec<FileId>;
+
+    fn file_len(&self, file: FileId) -> usize;
+}
+
+fn file_len(db: &dyn SourceDatabase, file: FileId) -> usize {
+    db.file_text(file).len()
+}
+
+#[test]
+fn borrowed_getters() {
+    let mut db = salsa::DatabaseImpl::new();
+
+    db.set_file_text(FileId(0), String::from("fn main() {}"));
+    db.set_crate_graph(vec![FileId(0)]);
+
+    let text: &String = db.file_text(FileId(0));
+    assert_eq!(text, "fn main() {}");
+    assert_eq!(db.crate_graph(), &[FileId(0)]);
+    assert_eq!(db.try_file_text(FileId(1)), None);
+    assert_eq!(db.file_len(FileId(0)), 12);
+}
+
+#[test]
+fn update_borrowed_input() {
+    let mut db = salsa::DatabaseImpl::new();
+
+    db.set_crate_graph(vec![FileId(0)]);
+    db.update_crate_graph(|graph| graph.push(FileId(1)));
+    db.set_crate_graph(vec![FileId(0), FileId(1)]);
+    assert_eq!(db.crate_graph(), &[FileId(0), FileId(1)]);
+
+    db.set_file_text(FileId(0), String::from("fn main() {"));
+    db.update_file_text(FileId(0), |text| text.push('}'));
+    assert_eq!(db.file_text(FileId(0)), "fn main() {}");
+}
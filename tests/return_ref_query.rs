@@ -0,0 +1,73 @@
+use expect_test::expect;
+
+mod logger_db;
+use logger_db::LoggerDb;
+use query_group::query_group;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ItemTree {
+    items: Vec<String>,
+}
+
+#[query_group]
+pub trait ItemTreeDatabase: salsa::Database {
+    #[salsa::input]
+    fn input_string(&self) -> String;
+
+    #[salsa::return_ref]
+    fn item_tree(&self) -> ItemTree;
+
+    #[salsa::return_ref]
+    #[salsa::invoke(invoked_words)]
+    fn words(&self, key: ()) -> Vec<String>;
+
+    fn item_count(&self) -> usize;
+}
+
+fn item_tree(db: &dyn ItemTreeDatabase) -> ItemTree {
+    let items = db.input_string().split(';').map(String::from).collect();
+    ItemTree { items }
+}
+
+fn invoked_words(db: &dyn ItemTreeDatabase, key: ()) -> Vec<String> {
+    let _ = key;
+    db.input_string().split(' ').map(String::from).collect()
+}
+
+fn item_count(db: &dyn ItemTreeDatabase) -> usize {
+    db.item_tree().items.len()
+}
+
+#[test]
+fn borrowed_query_results() {
+    let mut db = salsa::DatabaseImpl::new();
+
+    db.set_input_string(String::from("struct S;fn f() {}"));
+    let item_tree: &ItemTree = db.item_tree();
+    assert_eq!(item_tree.items, ["struct S", "fn f() {}"]);
+    let words: &Vec<String> = db.words(());
+    assert_eq!(words, &["struct", "S;fn", "f()", "{}"]);
+    assert_eq!(db.item_count(), 2);
+}
+
+#[test]
+fn rereading_borrows_the_memoized_value() {
+    let mut db = LoggerDb::default();
+
+    db.set_input_string(String::from("struct S;fn f() {}"));
+    let first: *const Vec<String> = db.words(());
+    let second: &Vec<String> = db.words(());
+    assert!(std::ptr::eq(first, second));
+    db.assert_logs(expect![[r#"
+        [
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillExecute { database_key: create_data_ItemTreeDatabase(Id(0)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(DidValidateMemoizedValue { database_key: create_data_ItemTreeDatabase(Id(0)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillExecute { database_key: words_shim(Id(800)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillCheckCancellation)",
+        ]"#]]);
+}
@@ -0,0 +1,32 @@
+use query_group::query_group;
+
+#[query_group(setters = separate)]
+pub trait HelloWorldDatabase: salsa::Database {
+    #[salsa::input]
+    fn input_string(&self) -> String;
+
+    fn length(&self) -> usize;
+}
+
+fn length(db: &dyn HelloWorldDatabase) -> usize {
+    db.input_string().len()
+}
+
+fn analyze(db: &dyn HelloWorldDatabase) -> (bool, usize) {
+    (db.has_input_string(), db.length())
+}
+
+fn configure(db: &mut dyn HelloWorldDatabaseInputs) {
+    db.set_input_string(String::from("Hello, world!"));
+}
+
+#[test]
+fn setters_in_extension_trait() {
+    let mut db = salsa::DatabaseImpl::new();
+
+    configure(&mut db);
+    assert_eq!(analyze(&db), (true, 13));
+
+    db.unset_input_string();
+    assert_eq!(db.try_input_string(), None);
+}
@@ -0,0 +1,108 @@
+use expect_test::expect;
+
+mod logger_db;
+use logger_db::LoggerDb;
+use query_group::query_group;
+
+#[query_group]
+pub trait TrackedOptionsDatabase: salsa::Database {
+    #[salsa::input]
+    fn input_string(&self) -> String;
+
+    #[salsa::tracked_options(no_eq)]
+    fn length(&self) -> usize;
+
+    fn is_long(&self) -> bool;
+
+    #[salsa::tracked_options(lru = 1)]
+    #[salsa::invoke(invoked_digits)]
+    fn digits(&self, key: u32) -> usize;
+}
+
+fn length(db: &dyn TrackedOptionsDatabase) -> usize {
+    db.input_string().len()
+}
+
+fn is_long(db: &dyn TrackedOptionsDatabase) -> bool {
+    db.length() > 10
+}
+
+fn invoked_digits(_db: &dyn TrackedOptionsDatabase, key: u32) -> usize {
+    key.to_string().len()
+}
+
+#[test]
+fn no_eq_query() {
+    let mut db = LoggerDb::default();
+
+    db.set_input_string(String::from("Hello, world!"));
+    assert!(db.is_long());
+    db.assert_logs(expect![[r#"
+        [
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillExecute { database_key: create_data_TrackedOptionsDatabase(Id(0)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(DidValidateMemoizedValue { database_key: create_data_TrackedOptionsDatabase(Id(0)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillExecute { database_key: is_long_shim(Id(400)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillExecute { database_key: length_shim(Id(400)) })",
+            "salsa_event(WillCheckCancellation)",
+        ]"#]]);
+
+    // `length` doesn't change, but without `Eq` salsa can't tell, so `is_long`
+    // is executed again instead of being validated.
+    db.set_input_string(String::from("Goodbye, you!"));
+    assert!(db.is_long());
+    db.assert_logs(expect![[r#"
+        [
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(DidValidateMemoizedValue { database_key: create_data_TrackedOptionsDatabase(Id(0)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillExecute { database_key: length_shim(Id(400)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillExecute { database_key: is_long_shim(Id(400)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillCheckCancellation)",
+        ]"#]]);
+}
+
+#[test]
+fn lru_query() {
+    let mut db = LoggerDb::default();
+
+    db.set_input_string(String::from("Hello, world!"));
+    assert_eq!(db.digits(1), 1);
+    assert_eq!(db.digits(22), 2);
+    db.assert_logs(expect![[r#"
+        [
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillExecute { database_key: create_data_TrackedOptionsDatabase(Id(0)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(DidValidateMemoizedValue { database_key: create_data_TrackedOptionsDatabase(Id(0)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillExecute { database_key: digits_shim(Id(800)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillExecute { database_key: digits_shim(Id(801)) })",
+        ]"#]]);
+
+    // the new revision evicts the least recently used value: `digits(22)` is still
+    // memoized, `digits(1)` has to be executed again.
+    db.set_input_string(String::from("Goodbye, you!"));
+    assert_eq!(db.digits(22), 2);
+    assert_eq!(db.digits(1), 1);
+    db.assert_logs(expect![[r#"
+        [
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(DidValidateMemoizedValue { database_key: create_data_TrackedOptionsDatabase(Id(0)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(DidValidateMemoizedValue { database_key: digits_shim(Id(801)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillExecute { database_key: digits_shim(Id(800)) })",
+        ]"#]]);
+}
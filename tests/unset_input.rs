@@ -0,0 +1,59 @@
+use query_group::query_group;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FileId(u32);
+
+#[query_group]
+pub trait HelloWorldDatabase: salsa::Database {
+    #[salsa::input]
+    fn input_string(&self) -> String;
+
+    #[salsa::input]
+    fn file_text(&self, file: FileId) -> String;
+}
+
+#[test]
+fn try_and_has() {
+    let mut db = salsa::DatabaseImpl::new();
+
+    assert_eq!(db.try_input_string(), None);
+    assert!(!db.has_input_string());
+
+    db.set_input_string(String::from("Hello, world!"));
+    assert_eq!(db.try_input_string(), Some(String::from("Hello, world!")));
+    assert!(db.has_input_string());
+
+    db.unset_input_string();
+    assert_eq!(db.try_input_string(), None);
+    assert!(!db.has_input_string());
+
+    // unsetting an input that isn't set doesn't write anything.
+    let changed_at = db.input_string_changed_at();
+    db.unset_input_string();
+    assert_eq!(db.input_string_changed_at(), changed_at);
+}
+
+#[test]
+fn keyed_try_and_has() {
+    let mut db = salsa::DatabaseImpl::new();
+
+    db.set_file_text(FileId(0), String::from("fn main() {}"));
+    assert!(db.has_file_text(FileId(0)));
+    assert!(!db.has_file_text(FileId(1)));
+
+    db.unset_file_text(FileId(0));
+    assert_eq!(db.try_file_text(FileId(0)), None);
+    assert!(!db.has_file_text(FileId(0)));
+
+    // unsetting a key that was never set doesn't write anything.
+    let changed_at = db.file_text_changed_at(FileId(1));
+    db.unset_file_text(FileId(1));
+    assert_eq!(db.file_text_changed_at(FileId(1)), changed_at);
+}
+
+#[test]
+#[should_panic(expected = "HelloWorldDatabase::input_string was read before being set")]
+fn read_before_set() {
+    let db = salsa::DatabaseImpl::new();
+    db.input_string();
+}
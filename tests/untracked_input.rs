@@ -0,0 +1,144 @@
+use expect_test::expect;
+
+mod logger_db;
+use logger_db::LoggerDb;
+use query_group::query_group;
+
+#[query_group]
+pub trait ConfigDatabase: salsa::Database {
+    #[salsa::input]
+    fn source(&self) -> String;
+
+    #[salsa::input(untracked, default = 1)]
+    fn verbosity(&self) -> u32;
+
+    #[salsa::input(untracked)]
+    fn budget(&self) -> u32;
+
+    fn length(&self) -> usize;
+
+    fn budgeted_length(&self) -> usize;
+
+    fn budget_or_zero(&self) -> u32;
+
+    fn has_budget_query(&self) -> bool;
+}
+
+fn length(db: &dyn ConfigDatabase) -> usize {
+    // an untracked read: `length` doesn't depend on the verbosity.
+    let _ = db.verbosity();
+    db.source().len()
+}
+
+fn budgeted_length(db: &dyn ConfigDatabase) -> usize {
+    db.source().len().min(db.budget_tracked() as usize)
+}
+
+fn budget_or_zero(db: &dyn ConfigDatabase) -> u32 {
+    db.try_budget_tracked().unwrap_or(0)
+}
+
+fn has_budget_query(db: &dyn ConfigDatabase) -> bool {
+    db.has_budget_tracked()
+}
+
+#[test]
+fn untracked_reads_do_not_invalidate() {
+    let mut db = LoggerDb::default();
+
+    db.set_source(String::from("Hello, world!"));
+    assert_eq!(db.verbosity(), 1);
+    assert_eq!(db.length(), 13);
+    db.assert_logs(expect![[r#"
+        [
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillExecute { database_key: create_data_ConfigDatabase(Id(0)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(DidValidateMemoizedValue { database_key: create_data_ConfigDatabase(Id(0)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillExecute { database_key: length_shim(Id(400)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillCheckCancellation)",
+        ]"#]]);
+
+    db.set_verbosity(2);
+    assert_eq!(db.verbosity(), 2);
+    assert_eq!(db.length(), 13);
+    db.assert_logs(expect![[r#"
+        [
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(DidValidateMemoizedValue { database_key: create_data_ConfigDatabase(Id(0)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(DidValidateMemoizedValue { database_key: length_shim(Id(400)) })",
+        ]"#]]);
+
+    db.set_source(String::from("Hello"));
+    assert_eq!(db.length(), 5);
+    db.assert_logs(expect![[r#"
+        [
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(DidValidateMemoizedValue { database_key: create_data_ConfigDatabase(Id(0)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillExecute { database_key: length_shim(Id(400)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillCheckCancellation)",
+        ]"#]]);
+}
+
+#[test]
+fn tracked_reads_of_untracked_inputs() {
+    let mut db = LoggerDb::default();
+
+    db.set_source(String::from("Hello, world!"));
+    db.set_budget(5);
+    assert_eq!(db.budgeted_length(), 5);
+    db.assert_logs(expect![[r#"
+        [
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillExecute { database_key: create_data_ConfigDatabase(Id(0)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(DidValidateMemoizedValue { database_key: create_data_ConfigDatabase(Id(0)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(DidValidateMemoizedValue { database_key: create_data_ConfigDatabase(Id(0)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillExecute { database_key: budgeted_length_shim(Id(400)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillCheckCancellation)",
+        ]"#]]);
+
+    db.set_budget(100);
+    assert_eq!(db.budgeted_length(), 13);
+    db.assert_logs(expect![[r#"
+        [
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(DidValidateMemoizedValue { database_key: create_data_ConfigDatabase(Id(0)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillExecute { database_key: budgeted_length_shim(Id(400)) })",
+            "salsa_event(WillCheckCancellation)",
+            "salsa_event(WillCheckCancellation)",
+        ]"#]]);
+
+    db.unset_budget();
+    assert!(!db.has_budget());
+}
+
+#[test]
+fn tracked_try_and_has_of_untracked_inputs() {
+    let mut db = salsa::DatabaseImpl::new();
+
+    assert_eq!(db.budget_or_zero(), 0);
+    assert!(!db.has_budget_query());
+
+    db.set_budget(5);
+    assert_eq!(db.budget_or_zero(), 5);
+    assert!(db.has_budget_query());
+
+    db.unset_budget();
+    assert_eq!(db.budget_or_zero(), 0);
+    assert!(!db.has_budget_query());
+}
@@ -0,0 +1,87 @@
+use std::sync::Arc;
+
+use query_group::query_group;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FileId(u32);
+
+#[query_group]
+pub trait FilesDatabase: salsa::Database {
+    #[salsa::input]
+    fn files(&self) -> Arc<Vec<FileId>>;
+
+    #[salsa::input(default)]
+    fn counter(&self) -> u32;
+
+    #[salsa::input]
+    fn file_text(&self, file: FileId) -> String;
+
+    fn file_count(&self) -> usize;
+}
+
+fn file_count(db: &dyn FilesDatabase) -> usize {
+    db.files().len()
+}
+
+#[test]
+fn update_in_place() {
+    let mut db = salsa::DatabaseImpl::new();
+
+    db.set_files(Arc::new(vec![FileId(0)]));
+    assert_eq!(db.file_count(), 1);
+
+    db.update_files(|files| Arc::make_mut(files).push(FileId(1)));
+    assert_eq!(*db.files(), vec![FileId(0), FileId(1)]);
+    assert_eq!(db.file_count(), 2);
+
+    db.update_files_with_durability(
+        |files| Arc::make_mut(files).clear(),
+        salsa::Durability::HIGH,
+    );
+    assert_eq!(db.file_count(), 0);
+}
+
+#[test]
+fn update_without_copying() {
+    let mut db = salsa::DatabaseImpl::new();
+
+    db.set_files(Arc::new(vec![FileId(0)]));
+    let files = Arc::as_ptr(&db.files());
+
+    // the stored `Arc` is the only one left, so `make_mut` doesn't clone the `Vec`.
+    db.update_files(|files| Arc::make_mut(files).push(FileId(1)));
+    assert_eq!(Arc::as_ptr(&db.files()), files);
+    assert_eq!(*db.files(), vec![FileId(0), FileId(1)]);
+}
+
+#[test]
+fn update_default_and_keyed() {
+    let mut db = salsa::DatabaseImpl::new();
+
+    db.update_counter(|counter| *counter += 1);
+    db.update_counter(|counter| *counter += 1);
+    assert_eq!(db.counter(), 2);
+
+    db.set_file_text(FileId(0), String::from("fn main() {"));
+    db.update_file_text(FileId(0), |text| text.push('}'));
+    assert_eq!(db.file_text(FileId(0)), "fn main() {}");
+}
+
+#[test]
+#[should_panic(expected = "FilesDatabase::files was read before being set")]
+fn update_unset() {
+    let mut db = salsa::DatabaseImpl::new();
+    db.update_files(|files| Arc::make_mut(files).push(FileId(0)));
+}
+
+#[test]
+fn update_panicking() {
+    let mut db = salsa::DatabaseImpl::new();
+
+    db.set_file_text(FileId(0), String::from("fn main() {}"));
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        db.update_file_text(FileId(0), |_| panic!("update failed"));
+    }));
+    assert!(result.is_err());
+    assert_eq!(db.file_text(FileId(0)), "fn main() {}");
+}
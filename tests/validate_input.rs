@@ -0,0 +1,63 @@
+use query_group::query_group;
+
+fn sorted_and_deduplicated(crates: &[u32]) -> Result<(), String> {
+    if crates.windows(2).all(|pair| pair[0] < pair[1]) {
+        Ok(())
+    } else {
+        Err(format!(
+            "crates are not sorted and deduplicated: {crates:?}"
+        ))
+    }
+}
+
+#[query_group]
+pub trait CrateGraphDatabase: salsa::Database {
+    #[salsa::input(validate = sorted_and_deduplicated -> String)]
+    fn crates(&self) -> Vec<u32>;
+
+    #[salsa::input(validate = sorted_and_deduplicated -> String, skip_unchanged)]
+    fn proc_macro_crates(&self) -> Vec<u32>;
+}
+
+#[test]
+fn valid_values_are_stored() {
+    let mut db = salsa::DatabaseImpl::new();
+
+    db.set_crates(vec![1, 2, 3]);
+    assert_eq!(db.crates(), vec![1, 2, 3]);
+
+    assert!(db.try_set_crates(vec![4, 5]).is_ok());
+    assert_eq!(db.crates(), vec![4, 5]);
+}
+
+#[test]
+fn try_set_rejects_invalid_values() {
+    let mut db = salsa::DatabaseImpl::new();
+
+    db.set_crates(vec![1, 2, 3]);
+    // the validator's own error comes back, not a boxed one.
+    let error: String = db.try_set_crates(vec![2, 1]).unwrap_err();
+    assert_eq!(error, "crates are not sorted and deduplicated: [2, 1]");
+    assert_eq!(db.crates(), vec![1, 2, 3]);
+}
+
+#[test]
+#[should_panic(expected = "invalid value for `CrateGraphDatabase::crates`")]
+fn set_panics_on_invalid_values() {
+    let mut db = salsa::DatabaseImpl::new();
+    db.set_crates(vec![1, 1]);
+}
+
+#[test]
+fn try_set_skips_unchanged_values() {
+    let mut db = salsa::DatabaseImpl::new();
+
+    assert!(db.try_set_proc_macro_crates(vec![1, 2]).is_ok());
+    let changed_at = db.proc_macro_crates_changed_at();
+
+    assert!(db.try_set_proc_macro_crates(vec![1, 2]).is_ok());
+    assert_eq!(db.proc_macro_crates_changed_at(), changed_at);
+
+    assert!(db.try_set_proc_macro_crates(vec![1, 3]).is_ok());
+    assert_ne!(db.proc_macro_crates_changed_at(), changed_at);
+}